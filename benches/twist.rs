@@ -0,0 +1,118 @@
+// Times `PuzzleState::twist` over a seeded scramble of each built-in puzzle:
+// `cargo bench --bench twist`.
+//
+// As a baseline the same twists are made the way they were before pieces were bitmasks, on
+// the same pieces: each a `Vec` signature and a `Permutation` attitude, every piece copied
+// into a new list on each twist and the whole puzzle checked for being solved after it.
+// Times per piece are given too. The 11-cell once had nine times as many pieces, from a
+// generator that wasn't a symmetry; that was a fault in its definition, so it isn't counted.
+
+use std::{hint::black_box, sync::Arc, time::Instant};
+
+use alt_11_cell::puzzle::{
+    def::PuzzleDef,
+    perm::Permutation,
+    piece::{Piece, bits},
+    state::{PuzzleState, SCRAMBLE_LENGTH},
+    twist::Twist,
+};
+
+const ROUNDS: u32 = 200;
+
+// a piece as it was
+#[derive(Clone)]
+struct OldPiece {
+    sig: Vec<u8>,
+    att: Permutation,
+}
+
+impl OldPiece {
+    fn new(piece: &Piece) -> Self {
+        let mut sig = vec![0; piece.degree()];
+        for g in bits(piece.sig) {
+            sig[g] = 1;
+        }
+        Self {
+            sig,
+            att: Permutation::identity(piece.degree()),
+        }
+    }
+
+    fn rotate(&self, rot: &Permutation) -> Self {
+        let mut sig = vec![0; self.sig.len()];
+        for (i, &s) in self.sig.iter().enumerate() {
+            sig[rot.permute(i)] = s;
+        }
+        Self {
+            sig,
+            att: self.att.product(rot),
+        }
+    }
+
+    fn is_solved(&self) -> bool {
+        (0..self.sig.len()).all(|i| self.sig[i] == self.sig[self.att.permute(i)])
+    }
+}
+
+fn old_twist(pieces: &mut Vec<OldPiece>, twist: &Twist) -> bool {
+    *pieces = pieces
+        .iter()
+        .map(|p| match p.sig[twist.grip] {
+            1 => p.rotate(&twist.rot),
+            _ => p.clone(),
+        })
+        .collect();
+    pieces.iter().all(OldPiece::is_solved)
+}
+
+fn main() {
+    for name in PuzzleDef::builtin_names() {
+        println!("{name}:");
+        bench(Arc::new(PuzzleDef::named(name).unwrap()));
+    }
+}
+
+fn bench(def: Arc<PuzzleDef>) {
+    let twists = PuzzleState::scramble_twists(&def, 0, SCRAMBLE_LENGTH);
+    let mut state = PuzzleState::new(def.clone());
+    let n = state.pieces.len() as u32;
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for twist in &twists {
+            state.twist(black_box(twist));
+        }
+    }
+    let per_twist = start.elapsed() / (ROUNDS * SCRAMBLE_LENGTH);
+    println!(
+        "  twist: {per_twist:?} per twist, {:?} per piece ({n} pieces, solved: {})",
+        per_twist / n,
+        state.is_solved()
+    );
+
+    let mut pieces: Vec<OldPiece> = state.pieces.iter().map(OldPiece::new).collect();
+    let rounds = ROUNDS / 10;
+    let start = Instant::now();
+    for _ in 0..rounds {
+        for twist in &twists {
+            black_box(old_twist(&mut pieces, black_box(twist)));
+        }
+    }
+    let baseline = start.elapsed() / (rounds * SCRAMBLE_LENGTH);
+    println!(
+        "  baseline: {baseline:?} per twist, {:?} per piece, {:.1}x slower on the same pieces",
+        baseline / n,
+        baseline.as_secs_f64() / per_twist.as_secs_f64()
+    );
+
+    let start = Instant::now();
+    for seed in 0..ROUNDS as u64 {
+        let mut state = PuzzleState::new(def.clone());
+        state.scramble(seed, SCRAMBLE_LENGTH);
+        black_box(state.is_solved());
+    }
+    println!(
+        "  scramble: {:?} per {SCRAMBLE_LENGTH}-twist scramble",
+        start.elapsed() / ROUNDS
+    );
+}
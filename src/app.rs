@@ -1,32 +1,1568 @@
-use eframe::egui::{self, Event, PointerButton, Pos2};
+use std::{
+    fs, io,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
-use crate::puzzle::{setup11c, state::PuzzleState, view::PuzzleView, viewsettings::ViewSettings};
+use alt_11_cell::puzzle::{
+    def::{DefError, PuzzleDef},
+    export,
+    finder::{Finder, Progress, Request, Role},
+    group::Analysis,
+    log::{LogError, SolveLog},
+    macros::{self, Macro, MacroError},
+    metrics::MoveCount,
+    notation::{self, Move},
+    perm::Permutation,
+    piece::Piece,
+    solver::{SolveError, Solver, Step},
+    state::SCRAMBLE_LENGTH,
+    timer::{Reading, Session, Timer, format_time},
+    twist::Twist,
+    view::PuzzleView,
+    viewsettings::ViewSettings,
+};
+use eframe::egui::{self, Color32, Event, PointerButton, Pos2};
+
+use crate::keys::{self, Action, Keybindings};
+
+const KEYBINDINGS_KEY: &str = "keybindings";
+const VIEW_SETTINGS_KEY: &str = "view_settings";
+// the inspection time in seconds, empty for none
+const INSPECTION_KEY: &str = "inspection";
+
+// colour schemes are saved per puzzle
+fn colors_key(def: &PuzzleDef) -> String {
+    format!("colors {}", def.name)
+}
+
+// and so are macros and timing sessions
+fn macros_key(def: &PuzzleDef) -> String {
+    format!("macros {}", def.name)
+}
+
+fn session_key(def: &PuzzleDef) -> String {
+    format!("session {}", def.name)
+}
+
+// saved macros that didn't read, which are dropped
+fn skipped_macros(errors: &[MacroError]) -> Option<String> {
+    let first = errors.first()?;
+    Some(format!("Skipped {} saved macros: {first}", errors.len()))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FileAction {
+    OpenLog,
+    SaveLog,
+    ImportColors,
+    ExportColors,
+    ExportImage,
+    ExportSession,
+    ImportMacros,
+    ExportMacros,
+}
+
+enum About {
+    Computing(mpsc::Receiver<Analysis>),
+    Done(Analysis),
+}
+
+enum Solution {
+    // with the pieces being solved, which the steps start from
+    Computing {
+        rx: mpsc::Receiver<Result<Vec<Step>, SolveError>>,
+        expected: Vec<Piece>,
+    },
+    Failed(String),
+    // the steps, the next one to play and the pieces as they should be before it
+    Ready {
+        steps: Vec<Step>,
+        next: usize,
+        expected: Vec<Piece>,
+    },
+}
+
+// what to look for with the algorithm finder, and the search for it
+struct FinderWindow {
+    open: bool,
+    roles: Vec<(usize, Role)>,
+    others_stay: bool,
+    cells: String,
+    longest: usize,
+    error: Option<String>,
+    search: Option<Search>,
+}
+
+impl FinderWindow {
+    fn new() -> Self {
+        FinderWindow {
+            open: false,
+            roles: Vec::new(),
+            others_stay: true,
+            cells: String::new(),
+            longest: 6,
+            error: None,
+            search: None,
+        }
+    }
+}
+
+struct Search {
+    progress: mpsc::Receiver<Progress>,
+    cancel: Arc<AtomicBool>,
+    // the longest sequences tried through, and those found
+    tried: usize,
+    found: Vec<Vec<Twist>>,
+    running: bool,
+}
+
+// a search nobody is waiting for any more stops
+impl Drop for Search {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+fn role_color(role: Role) -> Color32 {
+    match role {
+        Role::Stay => Color32::from_rgb(80, 200, 120),
+        Role::Move => Color32::from_rgb(230, 80, 80),
+        Role::Turn => Color32::from_rgb(240, 200, 60),
+    }
+}
+
+// what the next click on the puzzle picks a cell and face for
+#[derive(Clone, Copy, PartialEq)]
+enum Pick {
+    Record,
+    Play(usize),
+}
+
+// a macro being recorded about the cell and face picked, as they were on screen
+struct Recording {
+    cell: usize,
+    face: usize,
+    orientation: Permutation,
+    // the length of the twist stack when recording started
+    start: usize,
+}
+
+struct FileDialog {
+    action: FileAction,
+    path: String,
+}
 
 pub struct App {
     puzzle: PuzzleView,
+    // the built-in puzzles as (name, title), and the one picked from the menu
+    builtins: Vec<(&'static str, String)>,
+    switch_to: Option<&'static str>,
+    move_input: String,
+    move_error: Option<String>,
+    file_dialog: Option<FileDialog>,
+    status: Option<String>,
+    seed_input: String,
+    show_about: bool,
+    about: Option<About>,
+    // for the pieces as they were when solving was asked for
+    solution: Option<Solution>,
+    finder: FinderWindow,
+    timer: Timer,
+    session: Session,
+    show_timer: bool,
+    macros: Vec<Macro>,
+    macro_name: String,
+    picking: Option<Pick>,
+    recording: Option<Recording>,
+    show_macros: bool,
+    keys: Keybindings,
+    show_keys: bool,
+    show_settings: bool,
+    panning: bool,
+    // the binding waiting for a key press in the keybindings window
+    capturing: Option<usize>,
+    // the cell and face that keyboard twists act on
+    key_cell: usize,
+    key_face: usize,
+    // set by keyboard twisting, cleared when the mouse moves
+    keyboard_mode: bool,
 }
 
 impl App {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>, def: PuzzleDef) -> Self {
+        let mut keys = Keybindings::new(&def);
+        let colors_key = colors_key(&def);
+        let session_key = session_key(&def);
+        let macros_key = macros_key(&def);
+        let mut macros = Vec::new();
+        let mut status = None;
+        let mut timer = Timer::new();
+        let mut session = Session::default();
+        let mut puzzle = PuzzleView::new(def);
+        if let Some(storage) = cc.storage {
+            // macros first, so that their keybindings have somewhere to go
+            if let Some(saved) = storage.get_string(&macros_key) {
+                let errors;
+                (macros, errors) = macros::parse(&puzzle.state.def, &saved);
+                status = skipped_macros(&errors);
+                keys.set_macros(macros.len());
+            }
+            if let Some(saved) = storage.get_string(KEYBINDINGS_KEY) {
+                keys.load(&saved);
+            }
+            if let Some(saved) = storage.get_string(VIEW_SETTINGS_KEY) {
+                puzzle.settings.load(&saved);
+            }
+            if let Some(saved) = storage.get_string(&colors_key) {
+                // a saved scheme that no longer fits the puzzle is dropped
+                let _ = puzzle.settings.load_colors(&saved);
+            }
+            if let Some(saved) = storage.get_string(INSPECTION_KEY) {
+                timer.inspection = saved.parse().ok().map(Duration::from_secs);
+            }
+            if let Some(saved) = storage.get_string(&session_key) {
+                session = Session::read_csv(&saved).unwrap_or_default();
+            }
+        }
         App {
-            puzzle: PuzzleView {
-                state: PuzzleState::generate(setup11c::base_pieces(), &setup11c::generators()),
-                was_scrambled: false,
-                alt_view: false,
-                filters: setup11c::filters(),
-                filter_idx: 0,
-                faces: setup11c::faces(),
-                settings: ViewSettings::default(),
-            },
+            puzzle,
+            builtins: PuzzleDef::builtin_names()
+                .map(|name| (name, PuzzleDef::named(name).unwrap().name))
+                .collect(),
+            switch_to: None,
+            move_input: String::new(),
+            move_error: None,
+            file_dialog: None,
+            status,
+            seed_input: String::new(),
+            show_about: false,
+            about: None,
+            solution: None,
+            finder: FinderWindow::new(),
+            timer,
+            session,
+            show_timer: false,
+            macros,
+            macro_name: String::new(),
+            picking: None,
+            recording: None,
+            show_macros: false,
+            keys,
+            show_keys: false,
+            show_settings: false,
+            panning: false,
+            capturing: None,
+            key_cell: 0,
+            key_face: 1,
+            keyboard_mode: false,
+        }
+    }
+
+    fn run(&mut self, action: Action, pointer: Pos2) {
+        let filters = self.puzzle.state.def.filters.len();
+        match action {
+            Action::AltView => self.puzzle.alt_view ^= true,
+            Action::CursorTwist { ccw } => self.puzzle.pointer_twist(pointer, ccw),
+            Action::NextFilter => {
+                self.puzzle.filter_idx = (self.puzzle.filter_idx + 1).min(filters - 1)
+            }
+            Action::PrevFilter => self.puzzle.filter_idx = self.puzzle.filter_idx.saturating_sub(1),
+            Action::Reset => {
+                self.puzzle.state.reset();
+                self.puzzle.snap();
+                self.timer.cancel();
+                self.puzzle.was_scrambled = false;
+            }
+            Action::ResetView => self.puzzle.reset_view(),
+            Action::ResetOrientation => self.puzzle.reset_orientation(),
+            Action::Scramble => self.scramble(rand::random()),
+            Action::Undo => self.puzzle.undo(),
+            Action::Redo => self.puzzle.redo(),
+            Action::SelectCell(cell) => {
+                self.key_cell = cell;
+                self.keyboard_mode = true;
+            }
+            Action::NextCell | Action::PrevCell => {
+                let degree = self.puzzle.state.degree;
+                let step = if action == Action::NextCell {
+                    1
+                } else {
+                    degree - 1
+                };
+                self.key_cell = (self.key_cell + step) % degree;
+                self.keyboard_mode = true;
+            }
+            Action::SelectFace(face) => {
+                self.key_face = face;
+                self.keyboard_mode = true;
+            }
+            Action::Twist(kind) => {
+                let twist = Move {
+                    cell: self.key_cell,
+                    face: self.key_face,
+                    kind,
+                }
+                .twist(&self.puzzle.state.def);
+                self.puzzle.twist_on_screen(&twist);
+                self.keyboard_mode = true;
+            }
+            // about the face picked from the keyboard, if that is what is shown
+            Action::Macro(n) => {
+                let spot = if self.keyboard_mode {
+                    Some((self.key_cell, self.key_face))
+                } else {
+                    self.puzzle.face_at(pointer)
+                };
+                if let Some((cell, face)) = spot {
+                    self.play_macro(n, cell, face);
+                }
+            }
+        }
+    }
+
+    fn show_keys(&mut self, ctx: &egui::Context) {
+        if let Some(i) = self.capturing {
+            let pressed = ctx.input(|input| {
+                input.events.iter().find_map(|e| match e {
+                    Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => Some((*key, *modifiers)),
+                    _ => None,
+                })
+            });
+            if let Some((key, modifiers)) = pressed {
+                if key != egui::Key::Escape {
+                    let mut modifiers = modifiers;
+                    modifiers.ctrl |= modifiers.mac_cmd;
+                    modifiers.mac_cmd = false;
+                    modifiers.command = false;
+                    self.keys.bindings[i].1 = Some(egui::KeyboardShortcut::new(modifiers, key));
+                }
+                self.capturing = None;
+            }
+        }
+
+        let clashes: Vec<Vec<Action>> = (0..self.keys.bindings.len())
+            .map(|i| self.keys.clashes(i))
+            .collect();
+        let mut open = self.show_keys;
+        egui::Window::new("Keybindings")
+            .open(&mut open)
+            .default_height(400.0)
+            .show(ctx, |ui| {
+                ui.label("Click a binding, then press the new key. Escape cancels.");
+                if clashes.iter().any(|c| !c.is_empty()) {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "Some keys are bound more than once; they do the first action listed.",
+                    );
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("keys").striped(true).show(ui, |ui| {
+                        for (i, (action, shortcut)) in self.keys.bindings.iter_mut().enumerate() {
+                            ui.label(action.label());
+                            let text = match (self.capturing, shortcut.as_ref()) {
+                                (Some(c), _) if c == i => "press a key...".to_string(),
+                                (_, Some(s)) => keys::format_shortcut(s),
+                                (_, None) => "none".to_string(),
+                            };
+                            if ui.button(text).clicked() {
+                                self.capturing = Some(i);
+                            }
+                            if ui.small_button("Clear").clicked() {
+                                *shortcut = None;
+                            }
+                            if let Some(first) = clashes[i].first() {
+                                let others = clashes[i].len() - 1;
+                                let text = match others {
+                                    0 => format!("also {}", first.label()),
+                                    _ => format!("also {} and {others} more", first.label()),
+                                };
+                                ui.colored_label(ui.visuals().warn_fg_color, text);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+                if ui.button("Reset to defaults").clicked() {
+                    self.keys = Keybindings::new(&self.puzzle.state.def);
+                }
+            });
+        self.show_keys = open;
+        if !open {
+            self.capturing = None;
+        }
+    }
+
+    fn scramble(&mut self, seed: u64) {
+        self.puzzle.state.reset();
+        self.puzzle.state.scramble(seed, SCRAMBLE_LENGTH);
+        self.puzzle.snap();
+        self.puzzle.was_scrambled = true;
+        self.timer.arm(Instant::now());
+    }
+
+    // replaces the puzzle, keeping the view settings and keybindings that still apply
+    fn switch_puzzle(&mut self, def: PuzzleDef, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let mut puzzle = PuzzleView::new(def);
+        puzzle.settings.load(&self.puzzle.settings.write());
+        let mut macros = Vec::new();
+        let mut status = None;
+        if let Some(storage) = frame.storage_mut() {
+            let old = &self.puzzle.state.def;
+            storage.set_string(&colors_key(old), self.puzzle.settings.write_colors(old));
+            if let Some(saved) = storage.get_string(&colors_key(&puzzle.state.def)) {
+                let _ = puzzle.settings.load_colors(&saved);
+            }
+            storage.set_string(&session_key(old), self.session.write_csv());
+            self.session = (storage.get_string(&session_key(&puzzle.state.def)))
+                .and_then(|saved| Session::read_csv(&saved).ok())
+                .unwrap_or_default();
+            storage.set_string(&macros_key(old), macros::write(old, &self.macros));
+            if let Some(saved) = storage.get_string(&macros_key(&puzzle.state.def)) {
+                let errors;
+                (macros, errors) = macros::parse(&puzzle.state.def, &saved);
+                status = skipped_macros(&errors);
+            }
+        }
+        let mut keys = Keybindings::new(&puzzle.state.def);
+        keys.set_macros(macros.len());
+        keys.load(&self.keys.write());
+        self.keys = keys;
+        self.macros = macros;
+        self.picking = None;
+        self.recording = None;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(puzzle.state.def.name.clone()));
+        self.puzzle = puzzle;
+        self.about = None;
+        self.solution = None;
+        self.finder = FinderWindow {
+            open: self.finder.open,
+            ..FinderWindow::new()
+        };
+        self.timer.cancel();
+        self.move_error = None;
+        self.status = status;
+        self.key_cell = 0;
+        self.key_face = 1;
+    }
+
+    fn open_log(&mut self, path: &str) -> Result<(), LogError> {
+        let def = self.puzzle.state.def.clone();
+        let log = SolveLog::load(&def, path)?;
+        self.puzzle.state = log.replay(def);
+        self.puzzle.snap();
+        self.puzzle.was_scrambled = !log.scramble.is_empty();
+        self.timer.cancel();
+        Ok(())
+    }
+
+    // the macros that read are kept even if others don't
+    fn import_macros(&mut self, path: &str) -> Result<(), MacroError> {
+        let (imported, errors) = macros::load(&self.puzzle.state.def, path)?;
+        self.macros.extend(imported);
+        self.keys.set_macros(self.macros.len());
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    // the spot a click picked, to record about or to play a macro at
+    fn pick(&mut self, pick: Pick, cell: usize, face: usize) {
+        match pick {
+            Pick::Record => {
+                self.recording = Some(Recording {
+                    cell,
+                    face,
+                    orientation: self.puzzle.orientation().clone(),
+                    start: self.puzzle.state.twist_stack.len(),
+                })
+            }
+            Pick::Play(n) => self.play_macro(n, cell, face),
+        }
+    }
+
+    // keeps the twists made since recording started, as they were seen on screen then
+    fn stop_recording(&mut self) {
+        let Some(recording) = self.recording.take() else {
+            return;
+        };
+        let stack = &self.puzzle.state.twist_stack;
+        let to_screen = recording.orientation.inverse();
+        let twists: Vec<Twist> = stack[recording.start.min(stack.len())..]
+            .iter()
+            .map(|t| t.conjugate(&to_screen))
+            .collect();
+        if twists.is_empty() {
+            self.status = Some("Nothing was recorded".to_string());
+            return;
+        }
+        macros::clean_name(&mut self.macro_name);
+        let name = match self.macro_name.trim() {
+            "" => format!("macro {}", self.macros.len() + 1),
+            name => name.to_string(),
+        };
+        self.macros.push(Macro {
+            name,
+            cell: recording.cell,
+            face: recording.face,
+            twists,
+        });
+        self.keys.set_macros(self.macros.len());
+        self.macro_name.clear();
+    }
+
+    fn play_macro(&mut self, n: usize, cell: usize, face: usize) {
+        let Some(m) = self.macros.get(n) else {
+            return;
+        };
+        self.timer.assist();
+        for twist in m.relocate(&self.puzzle.state.def, cell, face) {
+            self.puzzle.twist_on_screen(&twist);
+        }
+    }
+
+    fn save_log(&self, path: &str) -> Result<(), LogError> {
+        SolveLog::from_state(&self.puzzle.state).save(&self.puzzle.state.def, path)
+    }
+
+    // the mouse wheel zooms about the cursor; dragging with the middle button, or with the
+    // primary one from off the stickers, pans
+    fn navigate(&mut self, ui: &mut egui::Ui) {
+        let response = ui.interact(ui.max_rect(), ui.id().with("puzzle"), egui::Sense::drag());
+        if let Some(pos) = response.hover_pos() {
+            let (scroll, zoom) = ui.input(|i| (i.smooth_scroll_delta.y, i.zoom_delta()));
+            let factor = zoom * (scroll / 200.0).exp();
+            if factor != 1.0 {
+                self.puzzle.zoom_at(pos, factor);
+            }
+        }
+        if response.drag_started_by(PointerButton::Primary) {
+            let origin = ui.input(|i| i.pointer.press_origin());
+            self.panning = origin.is_some_and(|pos| !self.puzzle.hits(pos));
+        }
+        if response.dragged_by(PointerButton::Middle)
+            || response.dragged_by(PointerButton::Primary) && self.panning
+        {
+            self.puzzle.pan_by(response.drag_delta());
+        }
+    }
+
+    // a PNG if the name says so, otherwise SVG
+    fn export_image(&self, path: &str) -> io::Result<()> {
+        if Path::new(path)
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("png"))
+        {
+            export::write_png(&self.puzzle, path)
+        } else {
+            export::write_svg(&self.puzzle, path)
+        }
+    }
+
+    fn import_colors(&mut self, path: &str) -> Result<(), DefError> {
+        self.puzzle.settings.load_colors(&fs::read_to_string(path)?)
+    }
+
+    fn export_colors(&self, path: &str) -> Result<(), DefError> {
+        let def = &self.puzzle.state.def;
+        Ok(fs::write(path, self.puzzle.settings.write_colors(def))?)
+    }
+
+    fn show_settings(&mut self, ctx: &egui::Context) {
+        if !self.show_settings {
+            return;
+        }
+        let def = self.puzzle.state.def.clone();
+        let settings = &mut self.puzzle.settings;
+        let mut open_dialog = None;
+        let mut reset_view = false;
+        egui::SidePanel::right("settings").show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.heading("View");
+                ui.add(egui::Slider::new(&mut settings.edge_size, 0.0..=0.3).text("Edge size"));
+                ui.add(egui::Slider::new(&mut settings.gap_size, 0.0..=0.2).text("Gap size"));
+                ui.add(
+                    egui::Slider::new(&mut settings.alt_ridge_width, 0.0..=0.2)
+                        .text("Alt view ridge width"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut settings.zoom, 0.2..=20.0)
+                        .logarithmic(true)
+                        .text("Zoom"),
+                );
+                ui.checkbox(&mut settings.animate, "Animate twists");
+                ui.add_enabled(
+                    settings.animate,
+                    egui::Slider::new(&mut settings.twist_duration, 0.05..=1.0)
+                        .text("Twist duration (s)"),
+                );
+                ui.checkbox(&mut settings.preview, "Preview twist under cursor");
+                // zoom, pan and which cell is in the middle; the sizes above stay as set
+                reset_view = ui.button("Reset view").clicked();
+
+                ui.separator();
+                ui.heading("Colours");
+                egui::Grid::new("colors").show(ui, |ui| {
+                    for (n, color) in settings.colors.iter_mut().enumerate() {
+                        ui.label(format!("Cell {n}"));
+                        ui.color_edit_button_srgba(color);
+                        ui.end_row();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Import...").clicked() {
+                        open_dialog = Some(FileAction::ImportColors);
+                    }
+                    if ui.button("Export...").clicked() {
+                        open_dialog = Some(FileAction::ExportColors);
+                    }
+                    if ui.button("Reset colours").clicked() {
+                        settings.colors = ViewSettings::new(&def).colors;
+                    }
+                });
+            });
+        });
+        if reset_view {
+            self.puzzle.reset_view();
+            self.puzzle.reset_orientation();
+        }
+        if let Some(action) = open_dialog {
+            self.file_dialog = Some(FileDialog {
+                action,
+                path: "colors.txt".to_string(),
+            });
+        }
+    }
+
+    fn show_inspector(&mut self, ctx: &egui::Context) {
+        let Some(index) = self.puzzle.inspected else {
+            return;
+        };
+        let def = &self.puzzle.state.def;
+        let piece = &self.puzzle.state.pieces[index];
+        let roles = &mut self.finder.roles;
+        let mut open = true;
+        egui::Window::new("Piece inspector")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("piece").show(ui, |ui| {
+                    ui.label("Piece");
+                    ui.label(index.to_string());
+                    ui.end_row();
+                    ui.label("Type");
+                    ui.label(match def.sticker_shape(piece) {
+                        Some(shape) => shape.kind(),
+                        // cell centres have no stickers
+                        None if piece.sig.count_ones() == 1 => "centre",
+                        None => "unknown",
+                    });
+                    ui.end_row();
+                    ui.label("sig");
+                    let sig: Vec<String> = (0..piece.degree())
+                        .map(|g| piece.grip_state(g).to_string())
+                        .collect();
+                    ui.monospace(sig.join(" "));
+                    ui.end_row();
+                    ui.label("att");
+                    ui.monospace(piece.attitude().to_string());
+                    ui.end_row();
+                    ui.label("Solved");
+                    ui.label(if piece.is_solved() { "yes" } else { "no" });
+                    ui.end_row();
+                });
+                // marks the piece for the algorithm finder
+                ui.horizontal(|ui| {
+                    ui.label("Finder:");
+                    let marked = roles.iter().position(|&(i, _)| i == index);
+                    if ui.selectable_label(marked.is_none(), "free").clicked()
+                        && let Some(k) = marked
+                    {
+                        roles.remove(k);
+                    }
+                    for role in Role::ALL {
+                        let current = marked.is_some_and(|k| roles[k].1 == role);
+                        if ui.selectable_label(current, role.name()).clicked() {
+                            match marked {
+                                Some(k) => roles[k].1 = role,
+                                None => roles.push((index, role)),
+                            }
+                        }
+                    }
+                });
+            });
+        if !open {
+            self.puzzle.inspected = None;
+        }
+    }
+
+    fn show_about(&mut self, ctx: &egui::Context) {
+        if !self.show_about {
+            return;
+        }
+        let about = self.about.get_or_insert_with(|| {
+            // the analysis can take a while, so keep it off the UI thread
+            let (tx, rx) = mpsc::channel();
+            let def = self.puzzle.state.def.clone();
+            thread::spawn(move || tx.send(Analysis::new(&def)));
+            About::Computing(rx)
+        });
+        if let About::Computing(rx) = about
+            && let Ok(analysis) = rx.try_recv()
+        {
+            *about = About::Done(analysis);
+        }
+
+        let def = &self.puzzle.state.def;
+        egui::Window::new("About this puzzle")
+            .open(&mut self.show_about)
+            .show(ctx, |ui| {
+                ui.heading(&def.name);
+                ui.label(format!("{} cells", def.degree));
+                let About::Done(a) = about else {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Analysing the twist group...");
+                    });
+                    return;
+                };
+
+                let order = a.order.to_string();
+                if order.len() > 12 {
+                    ui.label(format!(
+                        "About {}.{}e{} reachable states",
+                        &order[..1],
+                        &order[1..4],
+                        order.len() - 1
+                    ));
+                } else {
+                    ui.label(format!("{order} reachable states"));
+                }
+                ui.collapsing("Exact number of states", |ui| {
+                    ui.add(egui::Label::new(&order).wrap());
+                });
+                ui.label(format!(
+                    "{} distinct twists acting on {} stickers",
+                    a.twists, a.stickers
+                ));
+
+                ui.separator();
+                egui::Grid::new("piece_types").striped(true).show(ui, |ui| {
+                    ui.strong("Cells per piece");
+                    ui.strong("Pieces");
+                    ui.strong("Position orbits");
+                    ui.strong("Sticker orbits");
+                    ui.strong("Parity");
+                    ui.end_row();
+                    for t in &a.types {
+                        ui.label(t.grips.to_string());
+                        ui.label(t.pieces.to_string());
+                        ui.label(format!("{:?}", t.position_orbits));
+                        ui.label(format!("{:?}", t.sticker_orbits));
+                        ui.label(if t.always_even { "always even" } else { "free" });
+                        ui.end_row();
+                    }
+                });
+                for &(i, j) in &a.linked_parities {
+                    ui.label(format!(
+                        "Pieces in {} and in {} cells always have the same parity",
+                        a.types[i].grips, a.types[j].grips
+                    ));
+                }
+            });
+    }
+
+    fn solve(&mut self) {
+        // like the analysis, solving the bigger puzzles takes a few seconds
+        let (tx, rx) = mpsc::channel();
+        let def = self.puzzle.state.def.clone();
+        let pieces = self.puzzle.state.pieces.clone();
+        self.solution = Some(Solution::Computing {
+            rx,
+            expected: pieces.clone(),
+        });
+        thread::spawn(move || tx.send(Solver::new(&def).solve(&pieces)));
+    }
+
+    fn show_solution(&mut self, ctx: &egui::Context) {
+        let Some(solution) = &mut self.solution else {
+            return;
+        };
+        if let Solution::Computing { rx, expected } = solution
+            && let Ok(result) = rx.try_recv()
+        {
+            *solution = match result {
+                Ok(steps) => Solution::Ready {
+                    steps,
+                    next: 0,
+                    expected: std::mem::take(expected),
+                },
+                Err(e) => Solution::Failed(e.to_string()),
+            };
+        }
+
+        let mut open = true;
+        let mut play = 0;
+        let mut again = false;
+        let def = &self.puzzle.state.def;
+        egui::Window::new("Solution")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let (steps, next, expected) = match solution {
+                    Solution::Computing { .. } => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Working out a solution...");
+                        });
+                        return;
+                    }
+                    Solution::Failed(e) => {
+                        ui.label(format!("No solution: {e}"));
+                        return;
+                    }
+                    Solution::Ready {
+                        steps,
+                        next,
+                        expected,
+                    } => (steps, *next, expected),
+                };
+                let twists: usize = steps.iter().map(|s| s.twists.len()).sum();
+                ui.label(format!("{} steps, {twists} twists", steps.len()));
+                if *expected != self.puzzle.state.pieces {
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        "The puzzle has changed since this was worked out",
+                    );
+                    if ui.button("Solve again").clicked() {
+                        again = true;
+                    }
+                    return;
+                }
+                ui.separator();
+                match steps.get(next) {
+                    Some(step) => {
+                        ui.strong(format!(
+                            "Step {} of {}: {}",
+                            next + 1,
+                            steps.len(),
+                            step.label
+                        ));
+                        ui.add(
+                            egui::Label::new(
+                                egui::RichText::new(notation::format_sequence(def, &step.twists))
+                                    .monospace(),
+                            )
+                            .wrap(),
+                        );
+                    }
+                    None => {
+                        ui.label("All steps played");
+                    }
+                }
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(next < steps.len(), |ui| {
+                        if ui.button("Next step").clicked() {
+                            play = 1;
+                        }
+                        if ui.button("Play all").clicked() {
+                            play = steps.len() - next;
+                        }
+                    });
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for (i, step) in steps.iter().enumerate() {
+                            let label =
+                                format!("{}. {} ({})", i + 1, step.label, step.twists.len());
+                            if i == next {
+                                ui.strong(label);
+                            } else {
+                                ui.label(label);
+                            }
+                        }
+                    });
+            });
+
+        if again {
+            self.solve();
+        } else if !open {
+            self.solution = None;
+        } else if let Some(Solution::Ready {
+            steps,
+            next,
+            expected,
+        }) = &mut self.solution
+        {
+            if play > 0 {
+                self.timer.assist();
+            }
+            for step in &steps[*next..*next + play] {
+                for twist in &step.twists {
+                    self.puzzle.twist_move(twist);
+                }
+            }
+            *next += play;
+            if play > 0 {
+                *expected = self.puzzle.state.pieces.clone();
+            }
+        }
+    }
+
+    fn find(&mut self) {
+        let finder = &mut self.finder;
+        let degree = self.puzzle.state.def.degree;
+        let cells: Result<Vec<usize>, _> = finder
+            .cells
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|c| !c.is_empty())
+            .map(|c| match c.parse() {
+                Ok(cell) if cell < degree => Ok(cell),
+                _ => Err(format!("no cell `{c}`")),
+            })
+            .collect();
+        let cells = match cells {
+            Ok(cells) => cells,
+            Err(e) => {
+                finder.error = Some(e);
+                return;
+            }
+        };
+        finder.error = None;
+        let request = Request {
+            // the pieces marked are looked for where they are now
+            roles: (finder.roles.iter())
+                .map(|&(index, role)| (self.puzzle.state.pieces[index].sig, role))
+                .collect(),
+            others_stay: finder.others_stay,
+            cells,
+            longest: finder.longest,
+        };
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let def = self.puzzle.state.def.clone();
+        let stop = cancel.clone();
+        thread::spawn(move || {
+            Finder::new(&def, &request).search(&stop, |progress| {
+                let _ = tx.send(progress);
+            })
+        });
+        finder.search = Some(Search {
+            progress: rx,
+            cancel,
+            tried: 0,
+            found: Vec::new(),
+            running: true,
+        });
+    }
+
+    fn show_finder(&mut self, ctx: &egui::Context) {
+        let finder = &mut self.finder;
+        if let Some(search) = &mut finder.search {
+            loop {
+                match search.progress.try_recv() {
+                    Ok(Progress::Depth(depth)) => search.tried = depth,
+                    Ok(Progress::Found(twists)) => search.found.push(twists),
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        search.running = false;
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut start = false;
+        let mut apply = None;
+        let def = &self.puzzle.state.def;
+        egui::Window::new("Algorithm finder")
+            .open(&mut finder.open)
+            .show(ctx, |ui| {
+                ui.label("Alt-click a piece and mark it in the piece inspector.");
+                let mut unmark = None;
+                egui::Grid::new("marked").striped(true).show(ui, |ui| {
+                    for (k, (index, role)) in finder.roles.iter_mut().enumerate() {
+                        ui.label(format!("Piece {index}"));
+                        let piece = &self.puzzle.state.pieces[*index];
+                        ui.label(def.sticker_shape(piece).map_or("centre", |s| s.kind()));
+                        egui::ComboBox::from_id_salt(("role", k))
+                            .selected_text(
+                                egui::RichText::new(role.name()).color(role_color(*role)),
+                            )
+                            .show_ui(ui, |ui| {
+                                for r in Role::ALL {
+                                    ui.selectable_value(role, r, r.name());
+                                }
+                            });
+                        if ui.button("Unmark").clicked() {
+                            unmark = Some(k);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(k) = unmark {
+                    finder.roles.remove(k);
+                }
+                ui.checkbox(&mut finder.others_stay, "Pieces not marked must stay");
+                ui.horizontal(|ui| {
+                    ui.label("Twist only cells:");
+                    ui.add(egui::TextEdit::singleline(&mut finder.cells).hint_text("all"));
+                });
+                ui.add(egui::Slider::new(&mut finder.longest, 1..=12).text("twists at most"));
+                if let Some(e) = &finder.error {
+                    ui.colored_label(Color32::RED, e);
+                }
+
+                let running = finder.search.as_ref().is_some_and(|s| s.running);
+                ui.horizontal(|ui| {
+                    if running {
+                        ui.spinner();
+                        if ui.button("Stop").clicked() {
+                            finder.search = None;
+                        }
+                    } else if ui
+                        .add_enabled(!finder.roles.is_empty(), egui::Button::new("Search"))
+                        .clicked()
+                    {
+                        start = true;
+                    }
+                });
+                let Some(search) = &finder.search else {
+                    return;
+                };
+                if search.found.is_empty() {
+                    ui.label(match (search.tried, running) {
+                        (0, true) => "Searching...".to_string(),
+                        (tried, true) => format!("No sequence of up to {tried} twists"),
+                        (tried, false) => format!("No sequence of up to {tried} twists does it"),
+                    });
+                }
+                for (k, twists) in search.found.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.button("Apply").clicked() {
+                            apply = Some(k);
+                        }
+                        ui.monospace(notation::format_sequence(def, twists));
+                    });
+                }
+            });
+
+        if start {
+            self.find();
+        }
+        if let Some(k) = apply
+            && let Some(search) = &self.finder.search
+        {
+            self.timer.assist();
+            for twist in search.found[k].clone() {
+                self.puzzle.twist_move(&twist);
+            }
+        }
+    }
+
+    fn show_timer(&mut self, ctx: &egui::Context) {
+        let mut export = false;
+        let mut clear = false;
+        let (timer, session) = (&mut self.timer, &self.session);
+        egui::Window::new("Timer")
+            .open(&mut self.show_timer)
+            .show(ctx, |ui| {
+                ui.label("Scrambling arms the timer; the first twist starts it.");
+                ui.horizontal(|ui| {
+                    let mut inspect = timer.inspection.is_some();
+                    let mut secs = timer.inspection.map_or(15, |d| d.as_secs());
+                    ui.checkbox(&mut inspect, "Inspection");
+                    ui.add_enabled(inspect, egui::DragValue::new(&mut secs).range(1..=60));
+                    ui.label("seconds");
+                    timer.inspection = inspect.then(|| Duration::from_secs(secs));
+                });
+                // splits are taken as the steps of the solving filter on show are done
+                for &(step, time) in timer.splits() {
+                    ui.label(format!("Step {step}: {}", format_time(time)));
+                }
+
+                ui.separator();
+                let stat = |d: Option<Duration>| d.map_or("-".to_string(), format_time);
+                egui::Grid::new("stats").show(ui, |ui| {
+                    for (label, value) in [
+                        ("Solves", session.timed().to_string()),
+                        (
+                            "Assisted",
+                            (session.solves.len() - session.timed()).to_string(),
+                        ),
+                        ("Mean", stat(session.mean())),
+                        ("Best", stat(session.best())),
+                        ("ao5", stat(session.average(5))),
+                        ("ao12", stat(session.average(12))),
+                    ] {
+                        ui.label(label);
+                        ui.label(value);
+                        ui.end_row();
+                    }
+                });
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for (i, solve) in session.solves.iter().enumerate().rev() {
+                            ui.label(format!(
+                                "{}. {} ({} twists{})",
+                                i + 1,
+                                format_time(solve.time),
+                                solve.twists,
+                                if solve.assisted { ", assisted" } else { "" }
+                            ));
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    if ui.button("Export CSV...").clicked() {
+                        export = true;
+                    }
+                    if ui.button("New session").clicked() {
+                        clear = true;
+                    }
+                });
+            });
+
+        if export {
+            self.file_dialog = Some(FileDialog {
+                action: FileAction::ExportSession,
+                path: "session.csv".to_string(),
+            });
+        }
+        if clear {
+            self.session = Session::default();
+        }
+    }
+
+    fn show_macros(&mut self, ctx: &egui::Context) {
+        let mut record = false;
+        let mut stop = false;
+        let mut cancel = false;
+        let mut play = None;
+        let mut delete = None;
+        let mut file = None;
+        let twisted = self.puzzle.state.twist_stack.len();
+        egui::Window::new("Macros")
+            .open(&mut self.show_macros)
+            .show(ctx, |ui| {
+                match (self.picking, &self.recording) {
+                    (Some(pick), _) => {
+                        ui.label(match pick {
+                            Pick::Record => "Click the face to record about.".to_string(),
+                            Pick::Play(n) => format!("Click the face to play macro {} at.", n + 1),
+                        });
+                        if ui.button("Cancel").clicked() {
+                            cancel = true;
+                        }
+                    }
+                    (None, Some(recording)) => {
+                        ui.label(format!(
+                            "Recording about {}.{}: {} twists",
+                            recording.cell,
+                            recording.face,
+                            twisted.saturating_sub(recording.start)
+                        ));
+                        ui.horizontal(|ui| {
+                            if ui.button("Stop").clicked() {
+                                stop = true;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                cancel = true;
+                            }
+                        });
+                    }
+                    (None, None) => {
+                        ui.horizontal(|ui| {
+                            ui.label("Name:");
+                            ui.text_edit_singleline(&mut self.macro_name);
+                            if ui.button("Record").clicked() {
+                                record = true;
+                            }
+                        });
+                    }
+                }
+
+                ui.separator();
+                egui::Grid::new("macros").striped(true).show(ui, |ui| {
+                    for (i, m) in self.macros.iter_mut().enumerate() {
+                        ui.label((i + 1).to_string());
+                        let name =
+                            ui.add(egui::TextEdit::singleline(&mut m.name).desired_width(120.0));
+                        if name.changed() {
+                            macros::clean_name(&mut m.name);
+                        }
+                        ui.label(format!("{}.{}", m.cell, m.face));
+                        ui.label(format!("{} twists", m.twists.len()))
+                            .on_hover_text(notation::format_sequence(
+                                &self.puzzle.state.def,
+                                &m.twists,
+                            ));
+                        if ui.button("Play at...").clicked() {
+                            play = Some(i);
+                        }
+                        if ui.button("Delete").clicked() {
+                            delete = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+                ui.label("A macro's key plays it about the face under the cursor.");
+                ui.horizontal(|ui| {
+                    if ui.button("Import...").clicked() {
+                        file = Some(FileAction::ImportMacros);
+                    }
+                    if ui.button("Export...").clicked() {
+                        file = Some(FileAction::ExportMacros);
+                    }
+                    if ui.button("Keybindings...").clicked() {
+                        self.show_keys = true;
+                    }
+                });
+            });
+
+        if record {
+            self.picking = Some(Pick::Record);
+        }
+        if stop {
+            self.stop_recording();
+        }
+        if cancel {
+            self.picking = None;
+            self.recording = None;
+        }
+        if let Some(i) = play {
+            self.picking = Some(Pick::Play(i));
+        }
+        if let Some(i) = delete {
+            self.macros.remove(i);
+            self.keys.remove_macro(i);
+            self.picking = None;
+        }
+        if let Some(action) = file {
+            self.file_dialog = Some(FileDialog {
+                action,
+                path: "macros.txt".to_string(),
+            });
+        }
+    }
+
+    fn show_menu(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            egui::MenuBar::new().ui(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    for (label, action, path) in [
+                        ("Open log...", FileAction::OpenLog, "solve.log"),
+                        ("Save log...", FileAction::SaveLog, "solve.log"),
+                        ("Export image...", FileAction::ExportImage, "puzzle.svg"),
+                    ] {
+                        if ui.button(label).clicked() {
+                            self.file_dialog = Some(FileDialog {
+                                action,
+                                path: path.to_string(),
+                            });
+                        }
+                    }
+                });
+                ui.menu_button("Puzzle", |ui| {
+                    let current = &self.puzzle.state.def.name;
+                    for (name, title) in &self.builtins {
+                        if ui.selectable_label(title == current, title).clicked() {
+                            self.switch_to = Some(name);
+                        }
+                    }
+                });
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut self.show_settings, "Settings panel");
+                    if ui.button("Reset zoom and pan").clicked() {
+                        self.puzzle.reset_view();
+                    }
+                    if ui.button("Put cell 0 back in the middle").clicked() {
+                        self.puzzle.reset_orientation();
+                    }
+                    if ui.button("Keybindings...").clicked() {
+                        self.show_keys = true;
+                    }
+                });
+                ui.menu_button("Tools", |ui| {
+                    if ui.button("Algorithm finder...").clicked() {
+                        self.finder.open = true;
+                    }
+                    if ui.button("Timer and session...").clicked() {
+                        self.show_timer = true;
+                    }
+                    if ui.button("Macros...").clicked() {
+                        self.show_macros = true;
+                    }
+                });
+                ui.menu_button("Help", |ui| {
+                    if ui.button("About this puzzle").clicked() {
+                        self.show_about = true;
+                    }
+                });
+                ui.menu_button("Scramble", |ui| {
+                    if ui.button("Random scramble").clicked() {
+                        self.scramble(rand::random());
+                    }
+                    if ui.button("Solve step by step").clicked() {
+                        self.solve();
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Seed:");
+                        ui.text_edit_singleline(&mut self.seed_input);
+                        if ui.button("Scramble").clicked() {
+                            match self.seed_input.trim().parse() {
+                                Ok(seed) => self.scramble(seed),
+                                Err(_) => {
+                                    self.status = Some(format!("bad seed `{}`", self.seed_input))
+                                }
+                            }
+                        }
+                    });
+                });
+                if let Some(seed) = self.puzzle.state.scramble_seed {
+                    ui.separator();
+                    ui.label(format!("Seed: {seed}"));
+                }
+                if let Some(status) = &self.status {
+                    ui.separator();
+                    ui.label(status);
+                }
+            });
+        });
+
+        let Some(dialog) = &mut self.file_dialog else {
+            return;
+        };
+        let title = match dialog.action {
+            FileAction::OpenLog => "Open solve log",
+            FileAction::SaveLog => "Save solve log",
+            FileAction::ImportColors => "Import colour scheme",
+            FileAction::ExportColors => "Export colour scheme",
+            FileAction::ExportImage => "Export image (.svg or .png)",
+            FileAction::ExportSession => "Export session (.csv)",
+            FileAction::ImportMacros => "Import macros",
+            FileAction::ExportMacros => "Export macros",
+        };
+        let mut done = None;
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Path:");
+                    let input = ui.text_edit_singleline(&mut dialog.path);
+                    if input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        done = Some(true);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("OK").clicked() {
+                        done = Some(true);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        done = Some(false);
+                    }
+                });
+            });
+
+        if let Some(confirmed) = done {
+            let FileDialog { action, path } = self.file_dialog.take().unwrap();
+            if confirmed {
+                let result = match action {
+                    FileAction::OpenLog => self.open_log(&path).map_err(|e| e.to_string()),
+                    FileAction::SaveLog => self.save_log(&path).map_err(|e| e.to_string()),
+                    FileAction::ImportColors => {
+                        self.import_colors(&path).map_err(|e| e.to_string())
+                    }
+                    FileAction::ExportColors => {
+                        self.export_colors(&path).map_err(|e| e.to_string())
+                    }
+                    FileAction::ExportImage => self.export_image(&path).map_err(|e| e.to_string()),
+                    FileAction::ExportSession => {
+                        fs::write(&path, self.session.write_csv()).map_err(|e| e.to_string())
+                    }
+                    FileAction::ImportMacros => {
+                        self.import_macros(&path).map_err(|e| e.to_string())
+                    }
+                    FileAction::ExportMacros => {
+                        let def = &self.puzzle.state.def;
+                        macros::save(def, &self.macros, &path).map_err(|e| e.to_string())
+                    }
+                };
+                self.status = Some(match result {
+                    Ok(()) => match action {
+                        FileAction::OpenLog
+                        | FileAction::ImportColors
+                        | FileAction::ImportMacros => format!("Opened {path}"),
+                        FileAction::SaveLog
+                        | FileAction::ExportColors
+                        | FileAction::ExportImage
+                        | FileAction::ExportSession
+                        | FileAction::ExportMacros => {
+                            format!("Saved {path}")
+                        }
+                    },
+                    Err(e) => format!("{path}: {e}"),
+                });
+            }
         }
     }
 }
 
 impl eframe::App for App {
-    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        storage.set_string(KEYBINDINGS_KEY, self.keys.write());
+        storage.set_string(VIEW_SETTINGS_KEY, self.puzzle.settings.write());
+        let def = &self.puzzle.state.def;
+        storage.set_string(&colors_key(def), self.puzzle.settings.write_colors(def));
+        let inspection = self.timer.inspection.map(|d| d.as_secs().to_string());
+        storage.set_string(INSPECTION_KEY, inspection.unwrap_or_default());
+        storage.set_string(&session_key(def), self.session.write_csv());
+        storage.set_string(&macros_key(def), macros::write(def, &self.macros));
+    }
+
+    fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
+        // the key that finishes a capture mustn't also run its old action
+        let capturing = self.capturing.is_some();
+        self.show_menu(ctx);
+        if let Some(name) = self.switch_to.take() {
+            let def = PuzzleDef::named(name).unwrap();
+            self.switch_puzzle(def, ctx, frame);
+        }
+        self.show_about(ctx);
+        self.show_solution(ctx);
+        self.show_finder(ctx);
+        self.show_timer(ctx);
+        self.show_macros(ctx);
+        self.show_keys(ctx);
+        self.show_inspector(ctx);
+        self.show_settings(ctx);
+
+        egui::TopBottomPanel::bottom("moves").show(ctx, |ui| {
+            let def = self.puzzle.state.def.clone();
+            let count = MoveCount::of(&self.puzzle.state);
+            ui.horizontal(|ui| {
+                for (i, (label, n, about)) in [
+                    (
+                        "Twists",
+                        count.twists,
+                        "every twist, and every turn of the whole puzzle, counts 1",
+                    ),
+                    (
+                        "Moves",
+                        count.moves,
+                        "turning the whole puzzle doesn't count",
+                    ),
+                    (
+                        "Merged",
+                        count.merged,
+                        "twists of one cell in a row count once",
+                    ),
+                    (
+                        "Undos",
+                        count.undos,
+                        "undone twists, not in the other counts",
+                    ),
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    if i > 0 {
+                        ui.separator();
+                    }
+                    ui.label(format!("{label}: {n}")).on_hover_text(about);
+                }
+            });
+            egui::ScrollArea::horizontal()
+                .stick_to_right(true)
+                .show(ui, |ui| {
+                    ui.label(notation::format_sequence(
+                        &def,
+                        &self.puzzle.state.twist_stack,
+                    ));
+                });
+
+            let branches = self.puzzle.state.history.branches();
+            if branches.len() > 1 {
+                let mut selected = None;
+                ui.horizontal(|ui| {
+                    ui.label("Redo branch:");
+                    for (i, (twist, len, current)) in branches.into_iter().enumerate() {
+                        let label = format!("{} ({len})", notation::format_twist(&def, twist));
+                        if ui.selectable_label(current, label).clicked() {
+                            selected = Some(i);
+                        }
+                    }
+                });
+                if let Some(i) = selected {
+                    self.puzzle.state.history.select_branch(i);
+                }
+            }
+
+            ui.horizontal(|ui| {
+                let input = ui.text_edit_singleline(&mut self.move_input);
+                if input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    match notation::parse_sequence(&def, &self.move_input) {
+                        Ok(twists) => {
+                            self.timer.assist();
+                            for twist in &twists {
+                                self.puzzle.twist_move(twist);
+                            }
+                            self.move_input.clear();
+                            self.move_error = None;
+                        }
+                        Err(e) => self.move_error = Some(e.to_string()),
+                    }
+                }
+                if let Some(e) = &self.move_error {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+            });
+        });
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            if self.puzzle.was_scrambled && self.puzzle.state.is_solved {
-                ui.label("Solved!");
+            self.puzzle.fit(ui.max_rect());
+            self.navigate(ui);
+
+            let now = Instant::now();
+            if let Some(solve) = self
+                .timer
+                .update(now, &self.puzzle.state, self.puzzle.filter_idx)
+            {
+                self.session.solves.push(solve);
+            }
+            match self.timer.reading(now) {
+                Reading::Idle => {
+                    if self.puzzle.was_scrambled && self.puzzle.state.is_solved() {
+                        ui.label("Solved!");
+                    }
+                }
+                Reading::Ready => {
+                    ui.label("Twist to start the timer");
+                }
+                Reading::Inspecting(left) => {
+                    ui.heading(format!("Inspection: {:.0}", left.as_secs_f32().ceil()));
+                    ctx.request_repaint();
+                }
+                Reading::Running(time) => {
+                    ui.heading(format_time(time));
+                    ctx.request_repaint();
+                }
+                Reading::Done(time) => {
+                    ui.heading(format!("Solved! {}", format_time(time)));
+                }
             }
 
             let events = ui.input(|i| i.events.clone());
@@ -36,10 +1572,32 @@ impl eframe::App for App {
                     pos,
                     button,
                     pressed: true,
-                    modifiers: _,
+                    modifiers,
                 } = e
+                    && ui.max_rect().contains(pos)
+                    && ctx.layer_id_at(pos) == Some(ui.layer_id())
                 {
-                    if button == PointerButton::Primary {
+                    // alt-click opens the piece inspector and shift-click brings the cell
+                    // to the middle, instead of twisting
+                    if modifiers.alt {
+                        if button == PointerButton::Primary {
+                            self.puzzle.inspected = self.puzzle.piece_at(pos);
+                        }
+                    } else if modifiers.shift {
+                        if button == PointerButton::Primary
+                            && let Some(cell) = self.puzzle.cell_at(pos)
+                        {
+                            self.puzzle.recenter(cell);
+                        }
+                    } else if let Some(pick) = self.picking {
+                        // picking a spot for a macro, which doesn't twist
+                        if button == PointerButton::Primary
+                            && let Some((cell, face)) = self.puzzle.face_at(pos)
+                        {
+                            self.picking = None;
+                            self.pick(pick, cell, face);
+                        }
+                    } else if button == PointerButton::Primary {
                         self.puzzle.pointer_twist(pos, true);
                     } else if button == PointerButton::Secondary {
                         self.puzzle.pointer_twist(pos, false);
@@ -47,52 +1605,32 @@ impl eframe::App for App {
                 }
             }
 
-            if ui.input(|i| i.key_pressed(egui::Key::Space)) {
-                self.puzzle.alt_view ^= true;
+            // typing moves or picking a key must not trigger the shortcuts
+            if !ctx.wants_keyboard_input() && !capturing {
+                let pointer = ui.input(|i| i.pointer.latest_pos().unwrap_or_default());
+                for action in self.keys.pressed(ctx) {
+                    self.run(action, pointer);
+                }
             }
-
-            if ui.input(|i| i.key_pressed(egui::Key::S)) {
-                self.puzzle.pointer_twist(
-                    ui.input(|i| i.pointer.latest_pos().unwrap_or(Pos2::default())),
-                    true,
-                );
+            if ui.input(|i| i.pointer.delta() != egui::Vec2::ZERO) {
+                self.keyboard_mode = false;
             }
 
-            if ui.input(|i| i.key_pressed(egui::Key::D)) {
-                self.puzzle.pointer_twist(
-                    ui.input(|i| i.pointer.latest_pos().unwrap_or(Pos2::default())),
-                    false,
-                );
+            self.puzzle.show_puzzle(ui);
+            self.puzzle.show_inspected(ui);
+            if self.finder.open {
+                let marked: Vec<(usize, Color32)> = (self.finder.roles.iter())
+                    .map(|&(piece, role)| (piece, role_color(role)))
+                    .collect();
+                self.puzzle.show_marked(&marked, ui);
             }
-
-            if ui.input(|i| i.key_pressed(egui::Key::ArrowRight))
-                && self.puzzle.filter_idx < self.puzzle.filters.len() - 1
+            if self.keyboard_mode {
+                self.puzzle.show_selection(self.key_cell, self.key_face, ui);
+            } else if let Some(pos) = ui.input(|i| i.pointer.hover_pos())
+                && ui.rect_contains_pointer(ui.max_rect())
             {
-                self.puzzle.filter_idx += 1;
-            }
-
-            if ui.input(|i| i.key_pressed(egui::Key::ArrowLeft)) && self.puzzle.filter_idx > 0 {
-                self.puzzle.filter_idx -= 1;
+                self.puzzle.show_preview(pos, ui);
             }
-
-            if ui.input(|i| i.modifiers.ctrl) {
-                if ui.input(|i| i.key_pressed(egui::Key::R)) {
-                    self.puzzle.state.reset();
-                    self.puzzle.was_scrambled = false;
-                }
-
-                if ui.input(|i| i.key_pressed(egui::Key::F)) {
-                    self.puzzle.state.reset();
-                    self.puzzle.state.scramble(1000);
-                    self.puzzle.was_scrambled = true;
-                }
-
-                if ui.input(|i| i.key_pressed(egui::Key::Z)) {
-                    self.puzzle.state.undo();
-                }
-            }
-
-            self.puzzle.show_puzzle(ui);
             ctx.request_repaint();
         });
     }
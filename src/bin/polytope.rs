@@ -0,0 +1,39 @@
+// Prints the permutation tables of a puzzle definition, including any derived from a
+// `schlafli` presentation, together with the signatures of the pieces seen on face 1 of
+// cell 0, which is what drawing a new puzzle's `face` and `sticker` lines needs.
+//
+//     polytope FILE
+
+use std::process::ExitCode;
+
+use alt_11_cell::puzzle::{def::PuzzleDef, state::PuzzleState};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [path] = &args[..] else {
+        eprintln!("usage: polytope FILE");
+        return ExitCode::from(2);
+    };
+    let def = match PuzzleDef::load(path) {
+        Ok(def) => def,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return ExitCode::from(2);
+        }
+    };
+
+    println!("# {}", def.name);
+    print!("{}", def.write_tables());
+
+    let faces: Vec<String> = def.face_grips.iter().map(|f| f.to_string()).collect();
+    println!("\n# faces of cell 0: {}", faces.join(" "));
+    println!("# pieces on face 1 of cell 0:");
+    let pieces = PuzzleState::generate(def.base_pieces.clone(), &def.generators);
+    for piece in pieces.iter().filter(|p| p.in_grip(0) && p.in_grip(1)) {
+        let sig: Vec<String> = (0..def.degree)
+            .map(|g| piece.grip_state(g).to_string())
+            .collect();
+        println!("#   {}", sig.join(" "));
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,112 @@
+// Replays a scramble and a solution without opening a window and checks that the puzzle
+// ends up solved. Exits with 1 if it isn't and 2 if the input can't be read.
+//
+//     verify [--puzzle NAME|FILE] LOG
+//     verify [--puzzle NAME|FILE] (--scramble MOVES | --seed N) [--solution MOVES]
+//
+// NAME is a built-in puzzle, e.g. `57cell`.
+
+use std::{process::ExitCode, sync::Arc};
+
+use alt_11_cell::puzzle::{
+    def::PuzzleDef,
+    log::SolveLog,
+    notation,
+    state::{PuzzleState, SCRAMBLE_LENGTH},
+};
+
+const USAGE: &str = "usage: verify [--puzzle NAME|FILE] LOG
+       verify [--puzzle NAME|FILE] (--scramble MOVES | --seed N) [--solution MOVES]";
+
+#[derive(Default)]
+struct Args {
+    puzzle: Option<String>,
+    log: Option<String>,
+    scramble: Option<String>,
+    seed: Option<u64>,
+    solution: Option<String>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("{name} needs a value"));
+            match arg.as_str() {
+                "--puzzle" => parsed.puzzle = Some(value("--puzzle")?),
+                "--scramble" => parsed.scramble = Some(value("--scramble")?),
+                "--solution" => parsed.solution = Some(value("--solution")?),
+                "--seed" => {
+                    let seed = value("--seed")?;
+                    parsed.seed = Some(seed.parse().map_err(|_| format!("bad seed `{seed}`"))?);
+                }
+                "-h" | "--help" => return Err(USAGE.to_string()),
+                flag if flag.starts_with("--") => return Err(format!("unknown option `{flag}`")),
+                _ if parsed.log.is_none() => parsed.log = Some(arg),
+                _ => return Err(format!("unexpected argument `{arg}`")),
+            }
+        }
+        // exactly one source for the scramble, and a log brings its own solution
+        let sources = [
+            parsed.log.is_some(),
+            parsed.scramble.is_some(),
+            parsed.seed.is_some(),
+        ];
+        if sources.iter().filter(|&&s| s).count() != 1
+            || parsed.log.is_some() && parsed.solution.is_some()
+        {
+            return Err(USAGE.to_string());
+        }
+        Ok(parsed)
+    }
+}
+
+fn replay(args: &Args) -> Result<PuzzleState, String> {
+    let def = match &args.puzzle {
+        Some(name) if let Some(def) = PuzzleDef::named(name) => def,
+        Some(path) => PuzzleDef::load(path).map_err(|e| format!("{path}: {e}"))?,
+        None => PuzzleDef::builtin(),
+    };
+    let def = Arc::new(def);
+
+    if let Some(path) = &args.log {
+        let log = SolveLog::load(&def, path).map_err(|e| format!("{path}: {e}"))?;
+        return Ok(log.replay(def));
+    }
+
+    let mut state = PuzzleState::new(def.clone());
+    if let Some(seed) = args.seed {
+        state.scramble(seed, SCRAMBLE_LENGTH);
+    }
+    if let Some(scramble) = &args.scramble {
+        let twists = notation::parse_sequence(&def, scramble).map_err(|e| e.to_string())?;
+        for twist in &twists {
+            state.twist(twist);
+        }
+        state.scramble = twists;
+    }
+    let solution = args.solution.as_deref().unwrap_or_default();
+    for twist in notation::parse_sequence(&def, solution).map_err(|e| e.to_string())? {
+        state.twist_move(&twist);
+    }
+    Ok(state)
+}
+
+fn main() -> ExitCode {
+    let state = match Args::parse(std::env::args().skip(1)).and_then(|args| replay(&args)) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let moves = state.twist_stack.len();
+    if state.is_solved() {
+        println!("solved in {moves} moves");
+        ExitCode::SUCCESS
+    } else {
+        println!("not solved after {moves} moves");
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,321 @@
+// Keybindings, saved in eframe's storage as `action: key` lines, e.g.
+//
+//     undo: Ctrl+Z
+//     cell 3: 4
+//     vertex_twist 1 cw: Shift+J
+//
+// Besides the mouse-based shortcuts there is a keyboard-only scheme: one key picks a cell,
+// one a face of that cell, and one the kind and direction of the twist to make there. Only
+// the first twelve cells have keys of their own; `[` and `]` step through all of them.
+
+use alt_11_cell::puzzle::{def::PuzzleDef, notation::TwistKind};
+use eframe::egui::{self, Event, Key, KeyboardShortcut, Modifiers};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    AltView,
+    CursorTwist { ccw: bool },
+    NextFilter,
+    PrevFilter,
+    Reset,
+    ResetView,
+    ResetOrientation,
+    Scramble,
+    Undo,
+    Redo,
+    SelectCell(usize),
+    // step through every cell, for puzzles with more cells than keys
+    NextCell,
+    PrevCell,
+    SelectFace(usize),
+    Twist(TwistKind),
+    // plays a macro about the face under the cursor
+    Macro(usize),
+}
+
+impl Action {
+    // the name used in the saved bindings
+    fn name(&self) -> String {
+        let dir = |ccw: bool| if ccw { "ccw" } else { "cw" };
+        match *self {
+            Action::AltView => "alt_view".to_string(),
+            Action::CursorTwist { ccw } => format!("cursor_twist {}", dir(ccw)),
+            Action::NextFilter => "next_filter".to_string(),
+            Action::PrevFilter => "prev_filter".to_string(),
+            Action::Reset => "reset".to_string(),
+            Action::ResetView => "reset_view".to_string(),
+            Action::ResetOrientation => "reset_orientation".to_string(),
+            Action::Scramble => "scramble".to_string(),
+            Action::Undo => "undo".to_string(),
+            Action::Redo => "redo".to_string(),
+            Action::SelectCell(n) => format!("cell {n}"),
+            Action::NextCell => "next_cell".to_string(),
+            Action::PrevCell => "prev_cell".to_string(),
+            Action::SelectFace(n) => format!("face {n}"),
+            Action::Twist(TwistKind::Face { ccw }) => format!("face_twist {}", dir(ccw)),
+            Action::Twist(TwistKind::Vertex { vertex, ccw }) => {
+                format!("vertex_twist {vertex} {}", dir(ccw))
+            }
+            Action::Twist(TwistKind::Edge { edge }) => format!("edge_twist {edge}"),
+            Action::Macro(n) => format!("macro {n}"),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        let dir = |ccw: bool| if ccw { "counterclockwise" } else { "clockwise" };
+        match *self {
+            Action::AltView => "Toggle alternative view".to_string(),
+            Action::CursorTwist { ccw } => format!("Twist at cursor {}", dir(ccw)),
+            Action::NextFilter => "Next filter".to_string(),
+            Action::PrevFilter => "Previous filter".to_string(),
+            Action::Reset => "Reset".to_string(),
+            Action::ResetView => "Reset zoom and pan".to_string(),
+            Action::ResetOrientation => "Put cell 0 back in the middle".to_string(),
+            Action::Scramble => "Random scramble".to_string(),
+            Action::Undo => "Undo".to_string(),
+            Action::Redo => "Redo".to_string(),
+            Action::SelectCell(n) => format!("Select cell {n}"),
+            Action::NextCell => "Select the next cell".to_string(),
+            Action::PrevCell => "Select the previous cell".to_string(),
+            Action::SelectFace(n) => format!("Select face {n}"),
+            Action::Twist(TwistKind::Face { ccw }) => format!("Face twist {}", dir(ccw)),
+            Action::Twist(TwistKind::Vertex { vertex, ccw }) => {
+                format!("Vertex {vertex} twist {}", dir(ccw))
+            }
+            Action::Twist(TwistKind::Edge { edge }) => format!("Edge {edge} flip"),
+            Action::Macro(n) => format!("Play macro {}", n + 1),
+        }
+    }
+}
+
+pub struct Keybindings {
+    pub bindings: Vec<(Action, Option<KeyboardShortcut>)>,
+}
+
+impl Keybindings {
+    pub fn new(def: &PuzzleDef) -> Self {
+        let key = |modifiers, key| Some(KeyboardShortcut::new(modifiers, key));
+        let none = Modifiers::NONE;
+        let mut bindings = vec![
+            (Action::AltView, key(none, Key::Space)),
+            (Action::CursorTwist { ccw: true }, key(none, Key::S)),
+            (Action::CursorTwist { ccw: false }, key(none, Key::D)),
+            (Action::NextFilter, key(none, Key::ArrowRight)),
+            (Action::PrevFilter, key(none, Key::ArrowLeft)),
+            (Action::Reset, key(Modifiers::CTRL, Key::R)),
+            (Action::ResetView, key(none, Key::Home)),
+            (Action::ResetOrientation, key(none, Key::End)),
+            (Action::Scramble, key(Modifiers::CTRL, Key::F)),
+            (Action::Undo, key(Modifiers::CTRL, Key::Z)),
+            (Action::Redo, key(Modifiers::CTRL, Key::Y)),
+        ];
+
+        use Key::*;
+        let cell_keys = [
+            Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9, Num0, Minus, Equals,
+        ];
+        for cell in 0..def.degree {
+            let k = cell_keys.get(cell).and_then(|&k| key(none, k));
+            bindings.push((Action::SelectCell(cell), k));
+        }
+        bindings.push((Action::NextCell, key(none, CloseBracket)));
+        bindings.push((Action::PrevCell, key(none, OpenBracket)));
+        let face_keys = [Q, W, E, R, T, Y, U, I, O, P];
+        for (i, &face) in def.face_grips.iter().enumerate() {
+            let k = face_keys.get(i).and_then(|&k| key(none, k));
+            bindings.push((Action::SelectFace(face), k));
+        }
+
+        // plain keys turn counterclockwise, with shift clockwise
+        for ccw in [true, false] {
+            let m = if ccw { none } else { Modifiers::SHIFT };
+            bindings.push((Action::Twist(TwistKind::Face { ccw }), key(m, F)));
+        }
+        let n = def.face_rot(false).order() as usize;
+        let vertex_keys = [H, J, K, L, Semicolon];
+        for vertex in 0..n {
+            for ccw in [true, false] {
+                let m = if ccw { none } else { Modifiers::SHIFT };
+                let k = vertex_keys.get(vertex).and_then(|&k| key(m, k));
+                bindings.push((Action::Twist(TwistKind::Vertex { vertex, ccw }), k));
+            }
+        }
+        let edge_keys = [N, M, Comma, Period, Slash];
+        for edge in 0..n {
+            let k = edge_keys.get(edge).and_then(|&k| key(none, k));
+            bindings.push((Action::Twist(TwistKind::Edge { edge }), k));
+        }
+
+        Self { bindings }
+    }
+
+    // keeps a binding for each of `n` macros, unbound at first
+    pub fn set_macros(&mut self, n: usize) {
+        self.bindings
+            .retain(|(a, _)| !matches!(*a, Action::Macro(i) if i >= n));
+        for i in 0..n {
+            if !self.bindings.iter().any(|(a, _)| *a == Action::Macro(i)) {
+                self.bindings.push((Action::Macro(i), None));
+            }
+        }
+    }
+
+    // drops macro `n`'s binding, moving those of the macros after it down one
+    pub fn remove_macro(&mut self, n: usize) {
+        self.bindings.retain(|(a, _)| *a != Action::Macro(n));
+        for (action, _) in &mut self.bindings {
+            if let Action::Macro(i) = action
+                && *i > n
+            {
+                *i -= 1;
+            }
+        }
+    }
+
+    // applies saved bindings over the defaults; lines for actions this puzzle doesn't have
+    // are skipped
+    pub fn load(&mut self, src: &str) {
+        for line in src.lines() {
+            let Some((name, keys)) = line.split_once(':') else {
+                continue;
+            };
+            let keys = keys.trim();
+            let shortcut = if keys.is_empty() {
+                None
+            } else {
+                match parse_shortcut(keys) {
+                    Some(s) => Some(s),
+                    None => continue,
+                }
+            };
+            if let Some(binding) = self
+                .bindings
+                .iter_mut()
+                .find(|(a, _)| a.name() == name.trim())
+            {
+                binding.1 = shortcut;
+            }
+        }
+    }
+
+    pub fn write(&self) -> String {
+        let mut out = String::new();
+        for (action, shortcut) in &self.bindings {
+            let keys = shortcut.map(|s| format_shortcut(&s)).unwrap_or_default();
+            out += &format!("{}: {keys}\n", action.name());
+        }
+        out
+    }
+
+    // the actions whose keys were pressed this frame
+    pub fn pressed(&self, ctx: &egui::Context) -> Vec<Action> {
+        ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|e| match e {
+                    Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => self.action(*key, *modifiers),
+                    _ => None,
+                })
+                .collect()
+        })
+    }
+
+    fn action(&self, key: Key, modifiers: Modifiers) -> Option<Action> {
+        self.bindings.iter().find_map(|(action, shortcut)| {
+            let s = shortcut.as_ref()?;
+            pressed(s, key, modifiers).then_some(*action)
+        })
+    }
+
+    // the other actions bound to the same keys as binding `i`; of those, only the first in
+    // the list acts when the keys are pressed
+    pub fn clashes(&self, i: usize) -> Vec<Action> {
+        let Some(shortcut) = self.bindings[i].1 else {
+            return Vec::new();
+        };
+        self.bindings
+            .iter()
+            .enumerate()
+            .filter(|&(j, (_, s))| {
+                j != i && s.is_some_and(|s| pressed(&s, shortcut.logical_key, shortcut.modifiers))
+            })
+            .map(|(_, (action, _))| *action)
+            .collect()
+    }
+}
+
+// whether `key` with `modifiers` held presses `shortcut`, taking Cmd for Ctrl
+fn pressed(shortcut: &KeyboardShortcut, key: Key, modifiers: Modifiers) -> bool {
+    shortcut.logical_key == key
+        && shortcut.modifiers.ctrl == (modifiers.ctrl || modifiers.mac_cmd)
+        && shortcut.modifiers.shift == modifiers.shift
+        && shortcut.modifiers.alt == modifiers.alt
+}
+
+pub fn format_shortcut(shortcut: &KeyboardShortcut) -> String {
+    let m = shortcut.modifiers;
+    let mut out = String::new();
+    for (held, name) in [(m.ctrl, "Ctrl+"), (m.alt, "Alt+"), (m.shift, "Shift+")] {
+        if held {
+            out += name;
+        }
+    }
+    out + shortcut.logical_key.name()
+}
+
+fn parse_shortcut(s: &str) -> Option<KeyboardShortcut> {
+    let mut modifiers = Modifiers::NONE;
+    let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+    // `+` on its own is a key name too
+    let key = match parts.pop()? {
+        "" if parts.last() == Some(&"") => {
+            parts.pop();
+            "+"
+        }
+        key => key,
+    };
+    for part in parts {
+        match part {
+            "Ctrl" => modifiers.ctrl = true,
+            "Alt" => modifiers.alt = true,
+            "Shift" => modifiers.shift = true,
+            _ => return None,
+        }
+    }
+    Some(KeyboardShortcut::new(modifiers, Key::from_name(key)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clashes_are_found_both_ways() {
+        for name in PuzzleDef::builtin_names() {
+            let keys = Keybindings::new(&PuzzleDef::named(name).unwrap());
+            for i in 0..keys.bindings.len() {
+                assert_eq!(keys.clashes(i), [], "{:?}", keys.bindings[i].0);
+            }
+        }
+
+        let mut keys = Keybindings::new(&PuzzleDef::builtin());
+        let index = |keys: &Keybindings, action| {
+            keys.bindings
+                .iter()
+                .position(|(a, _)| *a == action)
+                .unwrap()
+        };
+        let (undo, redo) = (index(&keys, Action::Undo), index(&keys, Action::Redo));
+        keys.bindings[undo].1 = keys.bindings[redo].1;
+        assert_eq!(keys.clashes(undo), [Action::Redo]);
+        assert_eq!(keys.clashes(redo), [Action::Undo]);
+        keys.bindings[redo].1 = None;
+        assert_eq!(keys.clashes(undo), []);
+        assert_eq!(keys.clashes(redo), []);
+    }
+}
@@ -0,0 +1,4 @@
+// The puzzle model. `default-features = false` leaves out the egui view, for tools that
+// only need to twist and check puzzles.
+
+pub mod puzzle;
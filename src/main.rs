@@ -1,15 +1,33 @@
 // thanks to henrydukepickle and HactarCE for much of this code + the concepts behind it
 
 mod app;
-mod puzzle;
+mod keys;
+
+use alt_11_cell::puzzle::def::PuzzleDef;
+use eframe::egui;
 
 use crate::app::App;
 
 fn main() -> Result<(), eframe::Error> {
-    let native_options = eframe::NativeOptions::default();
+    // an optional built-in puzzle name or definition file replaces the 11-cell
+    let def = match std::env::args().nth(1) {
+        Some(arg) => PuzzleDef::named(&arg)
+            .map_or_else(|| PuzzleDef::load(&arg), Ok)
+            .unwrap_or_else(|e| {
+                eprintln!("{arg}: {e}");
+                std::process::exit(1);
+            }),
+        None => PuzzleDef::builtin(),
+    };
+
+    // a fixed app name keeps saved settings shared between puzzles
+    let native_options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_title(&def.name),
+        ..Default::default()
+    };
     eframe::run_native(
-        "Alternative 11-Cell",
+        "alt-11-cell",
         native_options,
-        Box::new(|cc| Ok(Box::new(App::new(cc)))),
+        Box::new(|cc| Ok(Box::new(App::new(cc, def)))),
     )
 }
@@ -0,0 +1,642 @@
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+use crate::puzzle::{
+    perm::Permutation,
+    piece::{self, MAX_DEGREE, Piece},
+    polytope::{Polytope, Tables},
+};
+
+// the puzzles that ship with the program, by the name they are chosen by
+const BUILTINS: [(&str, &str); 2] = [
+    ("alt11cell", include_str!("../../puzzles/alt11cell.txt")),
+    ("57cell", include_str!("../../puzzles/57cell.txt")),
+];
+
+#[derive(Debug)]
+pub enum DefError {
+    Io(io::Error),
+    Parse { line: usize, msg: String },
+}
+
+impl fmt::Display for DefError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefError::Io(e) => write!(f, "{e}"),
+            DefError::Parse { line: 0, msg } => write!(f, "{msg}"),
+            DefError::Parse { line, msg } => write!(f, "line {line}: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for DefError {}
+
+impl From<io::Error> for DefError {
+    fn from(e: io::Error) -> Self {
+        DefError::Io(e)
+    }
+}
+
+// Geometry is kept in plain numbers so the model doesn't depend on the GUI; the view
+// turns it into egui types.
+
+pub enum StickerShape {
+    Ridge,
+    Edge([usize; 4]),
+    Vertex([usize; 3]),
+}
+
+impl StickerShape {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StickerShape::Ridge => "ridge",
+            StickerShape::Edge(_) => "edge",
+            StickerShape::Vertex(_) => "vertex",
+        }
+    }
+}
+
+pub struct FaceShape {
+    pub grip: usize,
+    pub mirrored: bool,
+    pub vertices: Vec<[f32; 2]>,
+}
+
+pub struct PuzzleDef {
+    pub name: String,
+    pub degree: usize,
+    pub base_pieces: Vec<Piece>,
+    pub generators: Vec<Permutation>,
+    pub cell_recenter: Vec<Permutation>,
+    pub face_recenter: Vec<Permutation>,
+    // the grips of cell 0's faces: face 1 and those with a `face_recenter`
+    pub face_grips: Vec<usize>,
+    pub face_rot: [Permutation; 2],
+    pub vertex_rot: [Permutation; 2],
+    pub edge_rot: Permutation,
+    pub cell_positions: Vec<[f32; 2]>,
+    pub cell_scales: Vec<f32>,
+    pub cell_outline: Vec<[f32; 2]>,
+    pub faces: Vec<FaceShape>,
+    pub colors: Vec<[u8; 3]>,
+    pub stickers: Vec<(u64, StickerShape)>,
+    pub filters: Vec<Vec<u8>>,
+}
+
+impl PuzzleDef {
+    // the 11-cell, the first of the built-in puzzles
+    pub fn builtin() -> Self {
+        Self::named(BUILTINS[0].0).unwrap()
+    }
+
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTINS.iter().map(|&(name, _)| name)
+    }
+
+    // a built-in puzzle by name, e.g. `57cell`
+    pub fn named(name: &str) -> Option<Self> {
+        let &(_, src) = BUILTINS.iter().find(|&&(n, _)| n == name)?;
+        Some(Self::parse(src).expect("built-in puzzle definition is valid"))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DefError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(src: &str) -> Result<Self, DefError> {
+        let mut parser = Parser::default();
+        for (i, line) in src.lines().enumerate() {
+            parser.line = i + 1;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((head, values)) = line.split_once(':') else {
+                return Err(parser.error("expected `key: values`"));
+            };
+            let mut head = head.split_whitespace();
+            let key = head.next().unwrap_or_default();
+            let args: Vec<&str> = head.collect();
+            let values: Vec<&str> = values.split_whitespace().collect();
+            parser.entry(key, &args, &values)?;
+        }
+        parser.finish()
+    }
+
+    pub fn cell_recenter(&self, n: usize) -> &Permutation {
+        &self.cell_recenter[n]
+    }
+
+    pub fn face_recenter(&self, n: usize) -> &Permutation {
+        &self.face_recenter[n]
+    }
+
+    // the permutation tables in the definition file's format, e.g. to see the numbering of
+    // a derived puzzle
+    pub fn write_tables(&self) -> String {
+        let perm = |p: &Permutation| {
+            let images: Vec<String> = p.perm.iter().map(|i| i.to_string()).collect();
+            images.join(" ")
+        };
+        let mut out = format!("degree: {}\n\n", self.degree);
+        for piece in &self.base_pieces {
+            let sig: Vec<String> = (0..self.degree)
+                .map(|g| piece.grip_state(g).to_string())
+                .collect();
+            out += &format!("piece: {}\n", sig.join(" "));
+        }
+        out += "\n";
+        for g in &self.generators {
+            out += &format!("generator: {}\n", perm(g));
+        }
+        for (key, table) in [
+            ("cell_recenter", &self.cell_recenter),
+            ("face_recenter", &self.face_recenter),
+        ] {
+            out += "\n";
+            for (n, p) in table.iter().enumerate() {
+                if !p.is_identity() {
+                    out += &format!("{key} {n}: {}\n", perm(p));
+                }
+            }
+        }
+        out += &format!(
+            "\nface_rot ccw: {}\nface_rot cw: {}\nvertex_rot ccw: {}\nvertex_rot cw: {}\n\
+             edge_rot: {}\n",
+            perm(self.face_rot(true)),
+            perm(self.face_rot(false)),
+            perm(self.vertex_rot(true)),
+            perm(self.vertex_rot(false)),
+            perm(self.edge_rot()),
+        );
+        out
+    }
+
+    // the shape of a piece's stickers: turned the way the view draws one of them, with its
+    // cell centred and its face at face 1, the piece matches a `sticker` signature
+    pub fn sticker_shape(&self, piece: &Piece) -> Option<&StickerShape> {
+        (0..self.degree).find_map(|cell| {
+            let centered = piece.rotate(self.cell_recenter(cell));
+            (1..self.degree)
+                .filter(|&face| centered.in_grip(face))
+                .find_map(|face| {
+                    let sig = centered.rotate(self.face_recenter(face)).sig;
+                    self.stickers
+                        .iter()
+                        .find(|(s, _)| *s == sig)
+                        .map(|(_, shape)| shape)
+                })
+        })
+    }
+
+    pub fn face_rot(&self, ccw: bool) -> &Permutation {
+        &self.face_rot[ccw as usize]
+    }
+
+    pub fn vertex_rot(&self, ccw: bool) -> &Permutation {
+        &self.vertex_rot[ccw as usize]
+    }
+
+    pub fn edge_rot(&self) -> &Permutation {
+        &self.edge_rot
+    }
+}
+
+#[derive(Default)]
+struct Parser {
+    line: usize,
+    name: Option<String>,
+    degree: Option<usize>,
+    base_pieces: Vec<Piece>,
+    generators: Vec<Permutation>,
+    cell_recenter: Vec<(usize, Permutation)>,
+    face_recenter: Vec<(usize, Permutation)>,
+    face_rot: [Option<Permutation>; 2],
+    vertex_rot: [Option<Permutation>; 2],
+    edge_rot: Option<Permutation>,
+    // a polytope presentation, from which any tables not given are derived
+    schlafli: Option<[usize; 3]>,
+    relations: Vec<Vec<usize>>,
+    derived: Option<Tables>,
+    cells: Vec<(usize, [f32; 2], f32)>,
+    cell_outline: Vec<[f32; 2]>,
+    points: Vec<(usize, [f32; 2])>,
+    faces: Vec<(usize, bool, Vec<usize>)>,
+    colors: Vec<(usize, [u8; 3])>,
+    // with the line each was given on, for errors found once the faces are known
+    stickers: Vec<(usize, u64, StickerShape)>,
+    filters: Vec<Vec<u8>>,
+}
+
+impl Parser {
+    fn error(&self, msg: impl Into<String>) -> DefError {
+        DefError::Parse {
+            line: self.line,
+            msg: msg.into(),
+        }
+    }
+
+    fn entry(&mut self, key: &str, args: &[&str], values: &[&str]) -> Result<(), DefError> {
+        if !matches!(key, "name" | "schlafli" | "relation") {
+            self.derive()?;
+        }
+        if matches!(key, "schlafli" | "relation") && self.derived.is_some() {
+            return Err(self.error("the polytope must come before any table"));
+        }
+        match (key, args) {
+            ("name", []) => self.name = Some(values.join(" ")),
+            ("schlafli", []) => self.schlafli = Some(self.numbers::<usize, 3>(values)?),
+            // a word in the reflections ρ0..ρ3, optionally to a power
+            ("relation", power) => {
+                let power = match power {
+                    [] => 1,
+                    [n] => self.index(n)?,
+                    _ => return Err(self.error("expected `relation [power]`")),
+                };
+                let word = values
+                    .iter()
+                    .map(|s| match self.index(s)? {
+                        g @ 0..=3 => Ok(g),
+                        g => Err(self.error(format!("no reflection {g}, only 0 to 3"))),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.relations.push(word.repeat(power));
+            }
+            ("degree", []) => {
+                let [n] = self.numbers::<usize, 1>(values)?;
+                if n > MAX_DEGREE {
+                    return Err(self.error(format!("degree can be at most {MAX_DEGREE}")));
+                }
+                if let Some(tables) = &self.derived
+                    && tables.degree != n
+                {
+                    return Err(self.error(format!(
+                        "`degree` is {n} but the polytope has {} cells",
+                        tables.degree
+                    )));
+                }
+                self.degree = Some(n);
+            }
+            ("piece", []) => {
+                let sig = self.signature(values)?;
+                self.base_pieces.push(Piece::new(&sig));
+            }
+            ("generator", []) => {
+                let p = self.permutation(values)?;
+                self.generators.push(p);
+            }
+            ("cell_recenter", [n]) => {
+                let n = self.index(n)?;
+                let p = self.permutation(values)?;
+                self.cell_recenter.push((n, p));
+            }
+            ("face_recenter", [n]) => {
+                let n = self.index(n)?;
+                let p = self.permutation(values)?;
+                self.face_recenter.push((n, p));
+            }
+            ("face_rot" | "vertex_rot", [dir]) => {
+                let ccw = match *dir {
+                    "ccw" => true,
+                    "cw" => false,
+                    _ => return Err(self.error(format!("expected `ccw` or `cw`, got `{dir}`"))),
+                };
+                let p = self.permutation(values)?;
+                if key == "face_rot" {
+                    self.face_rot[ccw as usize] = Some(p);
+                } else {
+                    self.vertex_rot[ccw as usize] = Some(p);
+                }
+            }
+            ("edge_rot", []) => self.edge_rot = Some(self.permutation(values)?),
+            ("cell", [n]) => {
+                let n = self.index(n)?;
+                let [x, y, scale] = self.numbers::<f32, 3>(values)?;
+                self.cells.push((n, [x, y], scale));
+            }
+            ("outline", []) => {
+                let [x, y] = self.numbers::<f32, 2>(values)?;
+                self.cell_outline.push([x, y]);
+            }
+            ("point", [n]) => {
+                let n = self.index(n)?;
+                let [x, y] = self.numbers::<f32, 2>(values)?;
+                self.points.push((n, [x, y]));
+            }
+            ("face", [grip, rest @ ..]) => {
+                let grip = self.index(grip)?;
+                let mirrored = match rest {
+                    [] => false,
+                    ["mirrored"] => true,
+                    _ => return Err(self.error("expected `face <grip> [mirrored]`")),
+                };
+                let points = values
+                    .iter()
+                    .map(|s| self.index(s))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if points.len() < 3 {
+                    return Err(self.error("a face needs at least 3 points"));
+                }
+                self.faces.push((grip, mirrored, points));
+            }
+            ("color", [n]) => {
+                let n = self.index(n)?;
+                let rgb = self.numbers::<u8, 3>(values)?;
+                self.colors.push((n, rgb));
+            }
+            ("sticker", [kind, points @ ..]) => {
+                let points = points
+                    .iter()
+                    .map(|s| self.index(s))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let shape = match (*kind, &points[..]) {
+                    ("ridge", []) => StickerShape::Ridge,
+                    ("edge", &[a1, a0, b0, b1]) => StickerShape::Edge([a1, a0, b0, b1]),
+                    ("vertex", &[a, b, c]) => StickerShape::Vertex([a, b, c]),
+                    _ => {
+                        return Err(self.error(
+                            "expected `sticker ridge`, `sticker edge a1 a0 b0 b1` or `sticker vertex a b c`",
+                        ));
+                    }
+                };
+                let sig = self.signature(values)?;
+                self.stickers.push((self.line, piece::mask(&sig), shape));
+            }
+            ("filter", []) => {
+                let sig = self.signature(values)?;
+                self.filters.push(sig);
+            }
+            _ => return Err(self.error(format!("unknown entry `{key}`"))),
+        }
+        Ok(())
+    }
+
+    // enumerates the polytope, once its presentation is complete
+    fn derive(&mut self) -> Result<(), DefError> {
+        let Some(schlafli) = self.schlafli else {
+            return Ok(());
+        };
+        if self.derived.is_some() {
+            return Ok(());
+        }
+        let polytope = Polytope {
+            schlafli,
+            relations: std::mem::take(&mut self.relations),
+        };
+        let tables = polytope.tables().map_err(|e| self.error(e.to_string()))?;
+        self.degree = Some(tables.degree);
+        self.derived = Some(tables);
+        Ok(())
+    }
+
+    fn degree(&self) -> Result<usize, DefError> {
+        self.degree
+            .ok_or_else(|| self.error("`degree` must come before any table"))
+    }
+
+    fn index(&self, s: &str) -> Result<usize, DefError> {
+        s.parse()
+            .map_err(|_| self.error(format!("expected an index, got `{s}`")))
+    }
+
+    fn numbers<T: FromStr, const N: usize>(&self, values: &[&str]) -> Result<[T; N], DefError> {
+        let parsed = values
+            .iter()
+            .map(|s| {
+                s.parse()
+                    .map_err(|_| self.error(format!("expected a number, got `{s}`")))
+            })
+            .collect::<Result<Vec<T>, _>>()?;
+        parsed
+            .try_into()
+            .map_err(|_| self.error(format!("expected {N} values, got {}", values.len())))
+    }
+
+    fn signature(&self, values: &[&str]) -> Result<Vec<u8>, DefError> {
+        let degree = self.degree()?;
+        let sig = values
+            .iter()
+            .map(|s| match *s {
+                "0" => Ok(0),
+                "1" => Ok(1),
+                _ => Err(self.error(format!("expected 0 or 1, got `{s}`"))),
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+        if sig.len() != degree {
+            return Err(self.error(format!("expected {degree} values, got {}", sig.len())));
+        }
+        Ok(sig)
+    }
+
+    fn permutation(&self, values: &[&str]) -> Result<Permutation, DefError> {
+        let degree = self.degree()?;
+        let perm = values
+            .iter()
+            .map(|s| self.index(s))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut seen = vec![false; degree];
+        for &i in &perm {
+            if i >= degree || seen[i] {
+                return Err(self.error(format!("not a permutation of 0..{degree}")));
+            }
+            seen[i] = true;
+        }
+        if perm.len() != degree {
+            return Err(self.error(format!("expected {degree} values, got {}", perm.len())));
+        }
+        Ok(Permutation::new(perm))
+    }
+
+    fn finish(mut self) -> Result<PuzzleDef, DefError> {
+        self.line = 0;
+        self.derive()?;
+        let degree = self.degree()?;
+
+        // tables given in the file win over derived ones
+        let mut cell_recenter = vec![Permutation::identity(degree); degree];
+        let mut face_recenter = vec![Permutation::identity(degree); degree];
+        if let Some(tables) = self.derived.take() {
+            if self.base_pieces.is_empty() {
+                self.base_pieces = tables.base_pieces;
+            }
+            if self.generators.is_empty() {
+                self.generators = tables.generators;
+            }
+            for (table, derived) in [
+                (&mut self.face_rot, tables.face_rot),
+                (&mut self.vertex_rot, tables.vertex_rot),
+            ] {
+                for (p, d) in table.iter_mut().zip(derived) {
+                    p.get_or_insert(d);
+                }
+            }
+            self.edge_rot.get_or_insert(tables.edge_rot);
+            cell_recenter = tables.cell_recenter;
+            face_recenter = tables.face_recenter;
+        }
+
+        let missing = |what: &str| self.error(format!("missing `{what}`"));
+        if self.base_pieces.is_empty() {
+            return Err(missing("piece"));
+        }
+        if self.generators.is_empty() {
+            return Err(missing("generator"));
+        }
+        let [Some(face_cw), Some(face_ccw)] = self.face_rot.clone() else {
+            return Err(missing("face_rot"));
+        };
+        let [Some(vertex_cw), Some(vertex_ccw)] = self.vertex_rot.clone() else {
+            return Err(missing("vertex_rot"));
+        };
+        let edge_rot = self.edge_rot.clone().ok_or_else(|| missing("edge_rot"))?;
+
+        for (n, p) in &self.cell_recenter {
+            *self.slot(&mut cell_recenter, *n, "cell_recenter")? = p.clone();
+        }
+        for (n, p) in &self.face_recenter {
+            *self.slot(&mut face_recenter, *n, "face_recenter")? = p.clone();
+        }
+
+        let mut cell_positions = vec![[0.0; 2]; degree];
+        let mut cell_scales = vec![1.0; degree];
+        for &(n, pos, scale) in &self.cells {
+            *self.slot(&mut cell_positions, n, "cell")? = pos;
+            cell_scales[n] = scale;
+        }
+
+        let mut colors = vec![[0; 3]; degree];
+        for &(n, color) in &self.colors {
+            *self.slot(&mut colors, n, "color")? = color;
+        }
+
+        let mut points = Vec::new();
+        for &(n, pos) in &self.points {
+            if n >= points.len() {
+                points.resize(n + 1, None);
+            }
+            points[n] = Some(pos);
+        }
+        let mut faces = Vec::new();
+        for (grip, mirrored, indices) in &self.faces {
+            if *grip >= degree {
+                return Err(self.error(format!("face grip {grip} out of range")));
+            }
+            let vertices = indices
+                .iter()
+                .map(|&i| {
+                    points
+                        .get(i)
+                        .copied()
+                        .flatten()
+                        .ok_or_else(|| self.error(format!("face uses undefined point {i}")))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            faces.push(FaceShape {
+                grip: *grip,
+                mirrored: *mirrored,
+                vertices,
+            });
+        }
+
+        // any face may show any sticker, so its points must be on the smallest face
+        let corners = faces.iter().map(|f| f.vertices.len()).min().unwrap_or(0);
+        let mut stickers = Vec::new();
+        for (line, sig, shape) in std::mem::take(&mut self.stickers) {
+            let points: &[usize] = match &shape {
+                StickerShape::Ridge => &[],
+                StickerShape::Edge(p) => p,
+                StickerShape::Vertex(p) => p,
+            };
+            if let Some(&i) = points.iter().find(|&&i| i >= corners) {
+                self.line = line;
+                return Err(self.error(format!(
+                    "sticker point {i} out of range for a face of {corners} points"
+                )));
+            }
+            stickers.push((sig, shape));
+        }
+
+        if self.filters.is_empty() {
+            self.filters.push(vec![1; degree]);
+        }
+
+        Ok(PuzzleDef {
+            name: self.name.unwrap_or_else(|| "Custom Puzzle".to_string()),
+            degree,
+            base_pieces: self.base_pieces,
+            generators: self.generators,
+            cell_recenter,
+            face_grips: (1..degree)
+                .filter(|&n| n == 1 || !face_recenter[n].is_identity())
+                .collect(),
+            face_recenter,
+            face_rot: [face_cw, face_ccw],
+            vertex_rot: [vertex_cw, vertex_ccw],
+            edge_rot,
+            cell_positions,
+            cell_scales,
+            cell_outline: self.cell_outline,
+            faces,
+            colors,
+            stickers,
+            filters: self.filters,
+        })
+    }
+
+    fn slot<'a, T>(&self, table: &'a mut [T], n: usize, what: &str) -> Result<&'a mut T, DefError> {
+        table
+            .get_mut(n)
+            .ok_or_else(|| self.error(format!("`{what} {n}` out of range")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = include_str!("../../puzzles/alt11cell.txt");
+
+    // the built-in source with one line changed, and the error reading it gives
+    fn error_with(from: &str, to: &str) -> (usize, String) {
+        assert!(SRC.contains(from));
+        match PuzzleDef::parse(&SRC.replacen(from, to, 1)) {
+            Err(DefError::Parse { line, msg }) => (line, msg),
+            Err(e) => panic!("unexpected error {e}"),
+            Ok(_) => panic!("`{to}` was accepted"),
+        }
+    }
+
+    fn line_of(text: &str) -> usize {
+        SRC.lines().position(|l| l.starts_with(text)).unwrap() + 1
+    }
+
+    #[test]
+    fn builtins_parse() {
+        let def = PuzzleDef::parse(SRC).unwrap();
+        assert_eq!(def.degree, 11);
+        assert_eq!(def.faces.len(), 15);
+        assert_eq!(def.stickers.len(), 7);
+        for name in PuzzleDef::builtin_names() {
+            assert!(PuzzleDef::named(name).is_some());
+        }
+    }
+
+    #[test]
+    fn sticker_points_must_be_on_the_face() {
+        let (line, msg) = error_with("sticker edge 1 2 0 1:", "sticker edge 1 2 0 3:");
+        assert_eq!(line, line_of("sticker edge 1 2 0 1:"));
+        assert!(msg.contains("point 3"), "{msg}");
+    }
+
+    #[test]
+    fn stickers_need_their_number_of_points() {
+        let (line, _) = error_with("sticker vertex 2 0 1:", "sticker vertex 2 0:");
+        assert_eq!(line, line_of("sticker vertex 2 0 1:"));
+    }
+
+    #[test]
+    fn unknown_entries_are_errors() {
+        let (line, msg) = error_with("color 0:", "colour 0:");
+        assert_eq!(line, line_of("color 0:"));
+        assert!(msg.contains("unknown entry `colour`"), "{msg}");
+    }
+}
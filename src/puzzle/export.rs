@@ -0,0 +1,123 @@
+// Writes the puzzle as the view draws it to an SVG or PNG file, for documenting patterns and
+// algorithms. The layout is scaled to `WIDTH` pixels across whatever the zoom and pan.
+
+use std::{fmt::Write as _, fs, io, path::Path};
+
+use eframe::egui::Pos2;
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, Transform};
+
+use crate::puzzle::view::{PuzzleView, Shape};
+
+const WIDTH: f32 = 1200.0;
+
+// the shapes moved into image coordinates, with the image size
+fn layout(view: &PuzzleView) -> (Vec<Shape>, f32, f32) {
+    let (rect, shapes) = view.shapes();
+    let k = WIDTH / rect.width();
+    let map = |p: Pos2| ((p - rect.min) * k).to_pos2();
+    let shapes = shapes
+        .into_iter()
+        .map(|shape| match shape {
+            Shape::Circle {
+                center,
+                radius,
+                color,
+            } => Shape::Circle {
+                center: map(center),
+                radius: radius * k,
+                color,
+            },
+            Shape::Polygon { points, color } => Shape::Polygon {
+                points: points.into_iter().map(map).collect(),
+                color,
+            },
+        })
+        .collect();
+    (shapes, WIDTH, (rect.height() * k).ceil())
+}
+
+pub fn write_svg(view: &PuzzleView, path: impl AsRef<Path>) -> io::Result<()> {
+    let (shapes, width, height) = layout(view);
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\">\n"
+    );
+    for shape in shapes {
+        match shape {
+            Shape::Circle {
+                center,
+                radius,
+                color,
+            } => {
+                let (r, g, b, _) = color.to_tuple();
+                writeln!(
+                    out,
+                    "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{radius:.2}\" \
+                     fill=\"#{r:02x}{g:02x}{b:02x}\"/>",
+                    center.x, center.y
+                )
+                .unwrap();
+            }
+            Shape::Polygon { points, color } => {
+                let (r, g, b, _) = color.to_tuple();
+                let points: Vec<String> = points
+                    .iter()
+                    .map(|p| format!("{:.2},{:.2}", p.x, p.y))
+                    .collect();
+                writeln!(
+                    out,
+                    "<polygon points=\"{}\" fill=\"#{r:02x}{g:02x}{b:02x}\"/>",
+                    points.join(" ")
+                )
+                .unwrap();
+            }
+        }
+    }
+    out += "</svg>\n";
+    fs::write(path, out)
+}
+
+pub fn write_png(view: &PuzzleView, path: impl AsRef<Path>) -> io::Result<()> {
+    let (shapes, width, height) = layout(view);
+    let mut pixmap = Pixmap::new(width as u32, height as u32)
+        .ok_or_else(|| io::Error::other("image too large"))?;
+    let mut paint = Paint {
+        anti_alias: true,
+        ..Paint::default()
+    };
+    for shape in shapes {
+        let (path, color) = match shape {
+            Shape::Circle {
+                center,
+                radius,
+                color,
+            } => (PathBuilder::from_circle(center.x, center.y, radius), color),
+            Shape::Polygon { points, color } => (polygon_path(&points), color),
+        };
+        // degenerate shapes, e.g. with zero gap size, draw nothing
+        let Some(path) = path else {
+            continue;
+        };
+        let (r, g, b, a) = color.to_tuple();
+        paint.set_color_rgba8(r, g, b, a);
+        pixmap.fill_path(
+            &path,
+            &paint,
+            FillRule::Winding,
+            Transform::identity(),
+            None,
+        );
+    }
+    pixmap.save_png(path).map_err(io::Error::other)
+}
+
+fn polygon_path(points: &[Pos2]) -> Option<tiny_skia::Path> {
+    let (first, rest) = points.split_first()?;
+    let mut builder = PathBuilder::new();
+    builder.move_to(first.x, first.y);
+    for p in rest {
+        builder.line_to(p.x, p.y);
+    }
+    builder.close();
+    builder.finish()
+}
@@ -0,0 +1,364 @@
+// An algorithm finder: the user marks pieces that must move, turn in place or stay where they
+// are, and this searches for the shortest twist sequences doing that to a solved puzzle. The
+// search deepens one twist at a time over every twist of the chosen cells, cut off by how
+// many twists each marked piece needs at least to end up as asked, from a table worked out
+// for it first.
+
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::puzzle::{
+    def::PuzzleDef,
+    notation,
+    piece::{self, MAX_DEGREE, Piece, bits},
+    state::PuzzleState,
+    twist::Twist,
+};
+
+// how many sequences of the shortest length are looked for
+const RESULTS: usize = 8;
+// the bound for a piece that can never end up as asked
+const NEVER: u8 = u8::MAX;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    // back where it started, the same way round
+    Stay,
+    // anywhere else
+    Move,
+    // where it started, but turned
+    Turn,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Stay, Role::Move, Role::Turn];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Role::Stay => "stay",
+            Role::Move => "move",
+            Role::Turn => "turn",
+        }
+    }
+
+    fn fits(self, home: u64, piece: &Piece) -> bool {
+        let turned = bits(home).any(|c| piece.att[c] as usize != c);
+        match self {
+            Role::Stay => piece.sig == home && !turned,
+            Role::Move => piece.sig != home,
+            Role::Turn => piece.sig == home && turned,
+        }
+    }
+}
+
+pub struct Request {
+    // the places of the marked pieces, as the grips of the piece there, and their roles; a
+    // sequence found does the same to whatever pieces are in those places
+    pub roles: Vec<(u64, Role)>,
+    // whether the pieces not marked must stay too
+    pub others_stay: bool,
+    // the cells that may be twisted, or all of them if empty
+    pub cells: Vec<usize>,
+    pub longest: usize,
+}
+
+pub enum Progress {
+    // every sequence of this many twists has been tried
+    Depth(usize),
+    Found(Vec<Twist>),
+}
+
+pub struct Finder {
+    twists: Vec<Twist>,
+    tables: Vec<[u8; MAX_DEGREE]>,
+    undo: Vec<[u8; MAX_DEGREE]>,
+    // for each cell, the cells sharing no piece with it, whose twists commute with its own
+    apart: Vec<u64>,
+    // the pieces with a role as they start out, the marked ones first and then the others
+    // if they must stay
+    pieces: Vec<Piece>,
+    // for each marked piece, the fewest twists from each of its states to one fitting its role
+    distances: Vec<HashMap<(u64, u64), u8>>,
+    longest: usize,
+}
+
+impl Finder {
+    pub fn new(def: &PuzzleDef, request: &Request) -> Self {
+        let homes = PuzzleState::generate(def.base_pieces.clone(), &def.generators);
+        let cells = match &request.cells[..] {
+            [] => (0..def.degree).collect(),
+            cells => cells.to_vec(),
+        };
+        let twists: Vec<Twist> = cells
+            .iter()
+            .flat_map(|&cell| notation::cell_twists(def, cell))
+            .collect();
+        let apart = (0..def.degree)
+            .map(|c| {
+                (0..def.degree)
+                    .filter(|&d| d != c && !homes.iter().any(|p| p.in_grip(c) && p.in_grip(d)))
+                    .fold(0, |m, d| m | 1 << d)
+            })
+            .collect();
+
+        let mut pieces: Vec<Piece> = (request.roles.iter())
+            .map(|&(sig, _)| Piece::from_mask(sig, def.degree))
+            .collect();
+        if request.others_stay {
+            for home in &homes {
+                if !request.roles.iter().any(|&(sig, _)| sig == home.sig) {
+                    pieces.push(*home);
+                }
+            }
+        }
+
+        let mut finder = Self {
+            tables: twists.iter().map(|t| piece::table(&t.rot)).collect(),
+            undo: twists
+                .iter()
+                .map(|t| piece::table(&t.rot.inverse()))
+                .collect(),
+            twists,
+            apart,
+            pieces,
+            distances: Vec::new(),
+            longest: request.longest,
+        };
+        finder.distances = (request.roles.iter().zip(&finder.pieces))
+            .map(|(&(_, role), home)| finder.distances(home, role))
+            .collect();
+        finder
+    }
+
+    // every state the twists can take `home` to, then the distances back from the states
+    // fitting `role`; the twists of each cell include their inverses, so these are the same
+    // as the distances to them
+    fn distances(&self, home: &Piece, role: Role) -> HashMap<(u64, u64), u8> {
+        let mut states = HashMap::from([(key(home.sig, home), *home)]);
+        let mut frontier = vec![*home];
+        while !frontier.is_empty() {
+            let mut next = Vec::new();
+            for piece in frontier {
+                for turned in self.neighbours(&piece) {
+                    if states.insert(key(home.sig, &turned), turned).is_none() {
+                        next.push(turned);
+                    }
+                }
+            }
+            frontier = next;
+        }
+
+        let mut distances = HashMap::new();
+        let mut frontier: Vec<Piece> = states
+            .values()
+            .filter(|p| role.fits(home.sig, p))
+            .copied()
+            .collect();
+        for p in &frontier {
+            distances.insert(key(home.sig, p), 0);
+        }
+        let mut d = 0;
+        while !frontier.is_empty() {
+            d += 1;
+            let mut next = Vec::new();
+            for piece in frontier {
+                for turned in self.neighbours(&piece) {
+                    if let Entry::Vacant(entry) = distances.entry(key(home.sig, &turned)) {
+                        entry.insert(d);
+                        next.push(turned);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        distances
+    }
+
+    fn neighbours<'a>(&'a self, piece: &'a Piece) -> impl Iterator<Item = Piece> + 'a {
+        self.twists
+            .iter()
+            .zip(&self.tables)
+            .filter(|(t, _)| piece.in_grip(t.grip))
+            .map(|(_, table)| {
+                let mut turned = *piece;
+                turned.rotate_by(table);
+                turned
+            })
+    }
+
+    // reports the sequences as they are found, and each length once it is exhausted, until
+    // the shortest ones are all found, the longest allowed are tried or `cancel` is set
+    pub fn search(&self, cancel: &AtomicBool, report: impl FnMut(Progress)) {
+        let mut search = Search {
+            finder: self,
+            pieces: self.pieces.clone(),
+            path: Vec::new(),
+            found: 0,
+            moved: 0,
+            cancel,
+            report,
+        };
+        for depth in 1..=self.longest {
+            search.extend(depth);
+            if search.found > 0 || cancel.load(Ordering::Relaxed) {
+                return;
+            }
+            (search.report)(Progress::Depth(depth));
+        }
+    }
+}
+
+struct Search<'a, F> {
+    finder: &'a Finder,
+    pieces: Vec<Piece>,
+    path: Vec<usize>,
+    found: usize,
+    // how many of the pieces not marked are out of place, kept up to date by `turn`
+    moved: usize,
+    cancel: &'a AtomicBool,
+    report: F,
+}
+
+impl<F: FnMut(Progress)> Search<'_, F> {
+    fn extend(&mut self, depth: usize) {
+        if self.found == RESULTS || self.cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        if self.path.len() + self.bound() as usize > depth {
+            return;
+        }
+        let finder = self.finder;
+        if self.path.len() == depth {
+            // the bound is 0 only once every piece is as asked
+            self.found += 1;
+            (self.report)(Progress::Found(
+                self.path
+                    .iter()
+                    .map(|&t| finder.twists[t].clone())
+                    .collect(),
+            ));
+            return;
+        }
+        let last = self.path.last().map(|&t| finder.twists[t].grip);
+        for t in 0..finder.twists.len() {
+            let grip = finder.twists[t].grip;
+            // twists of one cell in a row are a single twist, and those of cells apart can
+            // go in either order
+            if let Some(last) = last
+                && (grip == last || finder.apart[last] >> grip & 1 == 1 && grip < last)
+            {
+                continue;
+            }
+            self.turn(grip, &finder.tables[t]);
+            self.path.push(t);
+            self.extend(depth);
+            self.path.pop();
+            self.turn(grip, &finder.undo[t]);
+        }
+    }
+
+    fn turn(&mut self, grip: usize, table: &[u8; MAX_DEGREE]) {
+        let marked = self.finder.distances.len();
+        for (i, p) in self.pieces.iter_mut().enumerate() {
+            if !p.in_grip(grip) {
+                continue;
+            }
+            let home = self.finder.pieces[i].sig;
+            let stayed = i >= marked && Role::Stay.fits(home, p);
+            p.rotate_by(table);
+            if i >= marked {
+                match (stayed, Role::Stay.fits(home, p)) {
+                    (true, false) => self.moved += 1,
+                    (false, true) => self.moved -= 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // how many twists it takes at least to make every piece as asked
+    fn bound(&self) -> u8 {
+        let finder = self.finder;
+        let mut bound = (self.moved > 0) as u8;
+        for (i, distances) in finder.distances.iter().enumerate() {
+            let home = finder.pieces[i].sig;
+            let d = distances
+                .get(&key(home, &self.pieces[i]))
+                .copied()
+                .unwrap_or(NEVER);
+            bound = bound.max(d);
+        }
+        bound
+    }
+}
+
+// a piece's position and where its grips have gone, packed six bits each
+fn key(home: u64, piece: &Piece) -> (u64, u64) {
+    let att = bits(home).fold(0, |k, c| k << 6 | piece.att[c] as u64);
+    (piece.sig, att)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    // marks on a scrambled puzzle the pieces a commutator of two cells moves there, and
+    // checks a sequence found moves those pieces and no others
+    #[test]
+    fn finds_sequence_for_pieces_where_they_are() {
+        let def = Arc::new(PuzzleDef::builtin());
+        let (a, b) = (
+            notation::cell_twists(&def, 0),
+            notation::cell_twists(&def, 2),
+        );
+        let commutator = [a[0].clone(), b[0].clone(), a[0].inverse(), b[0].inverse()];
+        let mut solved = PuzzleState::new(def.clone());
+        let homes = solved.pieces.clone();
+        for twist in &commutator {
+            solved.twist(twist);
+        }
+        let changed: Vec<(u64, Role)> = (homes.iter().zip(&solved.pieces))
+            .filter(|(home, piece)| home != piece)
+            .map(|(home, piece)| {
+                let role = if piece.sig == home.sig {
+                    Role::Turn
+                } else {
+                    Role::Move
+                };
+                (home.sig, role)
+            })
+            .collect();
+        assert!(!changed.is_empty());
+
+        let mut state = PuzzleState::new(def.clone());
+        state.scramble(7, 200);
+        let request = Request {
+            roles: changed.clone(),
+            others_stay: true,
+            cells: vec![0, 2],
+            longest: 4,
+        };
+        let mut found = None;
+        let cancel = AtomicBool::new(false);
+        Finder::new(&def, &request).search(&cancel, |progress| {
+            if let Progress::Found(twists) = progress {
+                found.get_or_insert(twists);
+                cancel.store(true, Ordering::Relaxed);
+            }
+        });
+        let found = found.expect("the commutator itself does it");
+
+        let before = state.pieces.clone();
+        for twist in &found {
+            state.twist(twist);
+        }
+        for (old, new) in before.iter().zip(&state.pieces) {
+            let marked = changed.iter().any(|&(sig, _)| sig == old.sig);
+            assert_eq!(old != new, marked);
+        }
+    }
+}
@@ -0,0 +1,572 @@
+// Group analysis of a puzzle: the twists act as permutations of the sticker slots (a piece
+// position together with one of its grips), and Schreier-Sims builds a stabilizer chain of
+// the group they generate. The chain is built from random elements and is accepted once
+// enough of them in a row sift through it, so the order is correct with overwhelming
+// probability rather than proven.
+
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    fmt,
+};
+
+use rand::{Rng, RngExt, SeedableRng, rngs::StdRng};
+
+use crate::puzzle::{
+    def::PuzzleDef, notation::Move, perm::Permutation, piece::Piece, twist::Twist,
+};
+
+const SIFT_CONFIDENCE: usize = 64;
+
+// a stabilizer chain G = G_0 > G_1 > ... > G_k = 1 with G_i fixing base[0..i]
+pub struct StabChain {
+    degree: usize,
+    base: Vec<usize>,
+    // strong generators with their inverses; one added at level j lies in G_0, ..., G_j
+    gens: Vec<(Permutation, Permutation, usize)>,
+    levels: Vec<Level>,
+}
+
+struct Level {
+    // inverse of a transversal element taking the base point to each orbit point
+    inv_transversal: Vec<Option<Permutation>>,
+    orbit: Vec<usize>,
+}
+
+impl StabChain {
+    pub fn new(gens: &[Permutation], rng: &mut impl Rng) -> Self {
+        let degree = gens.first().map_or(0, |g| g.deg);
+        let mut chain = Self {
+            degree,
+            base: Vec::new(),
+            gens: Vec::new(),
+            levels: Vec::new(),
+        };
+        let gens: Vec<Permutation> = gens.iter().filter(|g| !g.is_identity()).cloned().collect();
+        if gens.is_empty() {
+            return chain;
+        }
+        for g in &gens {
+            chain.add(g.clone());
+        }
+
+        let mut random = RandomElements::new(gens, rng);
+        let mut streak = 0;
+        while streak < SIFT_CONFIDENCE {
+            if chain.add(random.next(rng)) {
+                streak = 0;
+            } else {
+                streak += 1;
+            }
+        }
+        chain
+    }
+
+    // sifts g through the chain and returns the residue and the level it stopped at
+    fn sift(&self, g: Permutation) -> (Permutation, usize) {
+        self.sift_from(g, 0)
+    }
+
+    // as `sift`, for a g already fixing the base points before level `start`
+    fn sift_from(&self, mut g: Permutation, start: usize) -> (Permutation, usize) {
+        for (i, level) in self.levels.iter().enumerate().skip(start) {
+            match &level.inv_transversal[g.permute(self.base[i])] {
+                Some(u_inv) => g = g.product(u_inv),
+                None => return (g, i),
+            }
+        }
+        let len = self.levels.len();
+        (g, len)
+    }
+
+    // adds g to the chain if it doesn't already sift through, returning whether it did
+    fn add(&mut self, g: Permutation) -> bool {
+        let (h, j) = self.sift(g);
+        if j == self.levels.len() {
+            if h.is_identity() {
+                return false;
+            }
+            let point = (0..self.degree).find(|&p| h.permute(p) != p).unwrap();
+            let mut inv_transversal = vec![None; self.degree];
+            inv_transversal[point] = Some(Permutation::identity(self.degree));
+            self.base.push(point);
+            self.levels.push(Level {
+                inv_transversal,
+                orbit: vec![point],
+            });
+        }
+        let h_inv = h.inverse();
+        self.gens.push((h, h_inv, j));
+        for i in 0..=j {
+            self.extend_orbit(i);
+        }
+        true
+    }
+
+    // extends the orbit at level i after a strong generator was added
+    fn extend_orbit(&mut self, i: usize) {
+        let new = self.gens.len() - 1;
+        let level = &mut self.levels[i];
+        let gens: Vec<usize> = (0..self.gens.len())
+            .filter(|&k| self.gens[k].2 >= i)
+            .collect();
+        // the new generator may extend the orbit from any point, the old ones only from
+        // points found just now
+        let known = level.orbit.len();
+        let mut n = 0;
+        while n < level.orbit.len() {
+            let p = level.orbit[n];
+            let from = if n < known { &[new][..] } else { &gens };
+            for &k in from {
+                let (g, g_inv, _) = &self.gens[k];
+                let q = g.permute(p);
+                if level.inv_transversal[q].is_none() {
+                    let u_inv = level.inv_transversal[p].as_ref().unwrap();
+                    level.inv_transversal[q] = Some(g_inv.product(u_inv));
+                    level.orbit.push(q);
+                }
+            }
+            n += 1;
+        }
+    }
+
+    pub fn order(&self) -> BigUint {
+        let mut order = BigUint::one();
+        for level in &self.levels {
+            order.mul_small(level.orbit.len() as u32);
+        }
+        order
+    }
+}
+
+// product replacement, for roughly uniform random elements of the generated group
+struct RandomElements {
+    slots: Vec<Permutation>,
+    acc: Permutation,
+}
+
+impl RandomElements {
+    fn new(gens: Vec<Permutation>, rng: &mut impl Rng) -> Self {
+        let mut slots = gens.clone();
+        while slots.len() < 10 {
+            slots.extend(gens.iter().cloned());
+        }
+        let mut random = Self {
+            acc: Permutation::identity(slots[0].deg),
+            slots,
+        };
+        for _ in 0..50 {
+            random.next(rng);
+        }
+        random
+    }
+
+    fn next(&mut self, rng: &mut impl Rng) -> Permutation {
+        let n = self.slots.len();
+        let i = rng.random_range(0..n);
+        let j = (i + rng.random_range(1..n)) % n;
+        self.slots[i] = self.slots[i].product(&self.slots[j]);
+        self.acc = self.acc.product(&self.slots[i]);
+        self.acc.clone()
+    }
+}
+
+// just enough of an unsigned bignum to print group orders
+#[derive(Clone)]
+pub struct BigUint {
+    // base 10^9 digits, least significant first
+    digits: Vec<u32>,
+}
+
+impl BigUint {
+    fn one() -> Self {
+        Self { digits: vec![1] }
+    }
+
+    fn mul_small(&mut self, n: u32) {
+        let mut carry = 0u64;
+        for d in &mut self.digits {
+            let x = *d as u64 * n as u64 + carry;
+            *d = (x % 1_000_000_000) as u32;
+            carry = x / 1_000_000_000;
+        }
+        while carry > 0 {
+            self.digits.push((carry % 1_000_000_000) as u32);
+            carry /= 1_000_000_000;
+        }
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut digits = self.digits.iter().rev();
+        write!(f, "{}", digits.next().unwrap_or(&0))?;
+        for d in digits {
+            write!(f, "{d:09}")?;
+        }
+        Ok(())
+    }
+}
+
+pub struct PieceTypeInfo {
+    pub grips: usize,
+    pub pieces: usize,
+    pub position_orbits: Vec<usize>,
+    pub sticker_orbits: Vec<usize>,
+    pub always_even: bool,
+}
+
+pub struct Analysis {
+    pub order: BigUint,
+    pub stickers: usize,
+    pub twists: usize,
+    pub types: Vec<PieceTypeInfo>,
+    // pairs of piece types (by index into `types`) whose permutations always share parity
+    pub linked_parities: Vec<(usize, usize)>,
+}
+
+// the puzzle's twists as permutations of its sticker slots
+pub struct StickerAction {
+    pub positions: Vec<Piece>,
+    // (position, grip) for every sticker slot
+    pub points: Vec<(usize, usize)>,
+    position_index: HashMap<u64, usize>,
+    point_index: HashMap<(usize, usize), usize>,
+}
+
+impl StickerAction {
+    // the positions of the base pieces under the symmetries and the twists
+    pub fn new(def: &PuzzleDef, twists: &[Twist]) -> Self {
+        let mut positions: Vec<Piece> = Vec::new();
+        let mut position_index = HashMap::new();
+        for piece in &def.base_pieces {
+            if let Entry::Vacant(e) = position_index.entry(piece.sig) {
+                e.insert(positions.len());
+                positions.push(*piece);
+            }
+        }
+        let mut k = 0;
+        while k < positions.len() {
+            let piece = positions[k];
+            // twists only move the pieces in their grip
+            let twist_rots = twists
+                .iter()
+                .filter(|t| piece.in_grip(t.grip))
+                .map(|t| &t.rot);
+            let symmetries = def.cell_recenter.iter().chain(&def.face_recenter);
+            for rot in symmetries.chain(twist_rots) {
+                let new = Piece::from_mask(piece.rotate(rot).sig, piece.degree());
+                if let Entry::Vacant(e) = position_index.entry(new.sig) {
+                    e.insert(positions.len());
+                    positions.push(new);
+                }
+            }
+            k += 1;
+        }
+        let mut points = Vec::new();
+        let mut point_index = HashMap::new();
+        for (k, piece) in positions.iter().enumerate() {
+            for grip in 0..piece.degree() {
+                if piece.in_grip(grip) {
+                    point_index.insert((k, grip), points.len());
+                    points.push((k, grip));
+                }
+            }
+        }
+        Self {
+            positions,
+            points,
+            position_index,
+            point_index,
+        }
+    }
+
+    pub fn twist(&self, twist: &Twist) -> Permutation {
+        let perm = self
+            .points
+            .iter()
+            .map(|&(k, grip)| {
+                let piece = &self.positions[k];
+                if !piece.in_grip(twist.grip) {
+                    return self.point_index[&(k, grip)];
+                }
+                let new_k = self.position_index[&piece.rotate(&twist.rot).sig];
+                self.point_index[&(new_k, twist.rot.permute(grip))]
+            })
+            .collect();
+        Permutation::new(perm)
+    }
+
+    // the action of a sticker permutation on the positions of the pieces with `grips` grips
+    fn positions_of(&self, p: &Permutation, grips: usize) -> Permutation {
+        let of_type: Vec<usize> = (0..self.positions.len())
+            .filter(|&k| self.grip_count(k) == grips)
+            .collect();
+        let local: HashMap<usize, usize> =
+            of_type.iter().enumerate().map(|(i, &k)| (k, i)).collect();
+        let perm = of_type
+            .iter()
+            .map(|&k| {
+                let grip = self.positions[k].sig.trailing_zeros() as usize;
+                let (new_k, _) = self.points[p.permute(self.point_index[&(k, grip)])];
+                local[&new_k]
+            })
+            .collect();
+        Permutation::new(perm)
+    }
+
+    fn grip_count(&self, k: usize) -> usize {
+        self.positions[k].sig.count_ones() as usize
+    }
+}
+
+impl Analysis {
+    pub fn new(def: &PuzzleDef) -> Self {
+        let twists: Vec<Twist> = (0..def.degree)
+            .flat_map(|cell| Move::all(def, cell))
+            .map(|m| m.twist(def))
+            .collect();
+        let action = StickerAction::new(def, &twists);
+
+        let mut gens: Vec<Permutation> = Vec::new();
+        for twist in &twists {
+            let g = action.twist(twist);
+            if !gens.contains(&g) {
+                gens.push(g);
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let chain = StabChain::new(&gens, &mut rng);
+
+        let mut grip_counts: Vec<usize> = (0..action.positions.len())
+            .map(|k| action.grip_count(k))
+            .collect();
+        grip_counts.sort();
+        grip_counts.dedup();
+
+        let types: Vec<PieceTypeInfo> = grip_counts
+            .iter()
+            .map(|&grips| {
+                let of_type = |&(k, _): &(usize, usize)| action.grip_count(k) == grips;
+                let sticker_orbits = orbits(&gens, action.points.len())
+                    .into_iter()
+                    .filter(|orbit| of_type(&action.points[orbit[0]]))
+                    .map(|orbit| orbit.len())
+                    .collect();
+                let position_gens: Vec<Permutation> =
+                    gens.iter().map(|g| action.positions_of(g, grips)).collect();
+                let pieces = position_gens[0].deg;
+                PieceTypeInfo {
+                    grips,
+                    pieces,
+                    position_orbits: orbits(&position_gens, pieces)
+                        .into_iter()
+                        .map(|orbit| orbit.len())
+                        .collect(),
+                    sticker_orbits,
+                    always_even: position_gens.iter().all(|g| g.sign() == 1),
+                }
+            })
+            .collect();
+
+        // the parity of each type under each generator; equal columns are linked
+        let parities: Vec<Vec<bool>> = grip_counts
+            .iter()
+            .map(|&grips| {
+                gens.iter()
+                    .map(|g| action.positions_of(g, grips).is_odd())
+                    .collect()
+            })
+            .collect();
+        let mut linked_parities = Vec::new();
+        for a in 0..types.len() {
+            for b in a + 1..types.len() {
+                if !types[a].always_even && parities[a] == parities[b] {
+                    linked_parities.push((a, b));
+                }
+            }
+        }
+
+        Self {
+            order: chain.order(),
+            stickers: action.points.len(),
+            twists: gens.len(),
+            types,
+            linked_parities,
+        }
+    }
+}
+
+fn orbits(gens: &[Permutation], degree: usize) -> Vec<Vec<usize>> {
+    let mut seen = vec![false; degree];
+    let mut orbits = Vec::new();
+    for start in 0..degree {
+        if seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let mut orbit = vec![start];
+        let mut i = 0;
+        while i < orbit.len() {
+            for g in gens {
+                let q = g.permute(orbit[i]);
+                if !seen[q] {
+                    seen[q] = true;
+                    orbit.push(q);
+                }
+            }
+            i += 1;
+        }
+        orbits.push(orbit);
+    }
+    orbits
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use super::*;
+
+    const ELEVEN_CELL_ORDER: &str = "1651959701585761682028552711225689666454698031355252523397780823188225962202131883\
+        5561947553015519234604233616206804411035080298399735262987749037948283384204988919\
+        00979906249324192228496637952000000000000000000000000000000000000000";
+
+    fn from_cycles(degree: usize, cycles: &[&[usize]]) -> Permutation {
+        let mut perm: Vec<usize> = (0..degree).collect();
+        for cycle in cycles {
+            for (i, &p) in cycle.iter().enumerate() {
+                perm[p] = cycle[(i + 1) % cycle.len()];
+            }
+        }
+        Permutation::new(perm)
+    }
+
+    fn order(gens: &[Permutation], seed: u64) -> String {
+        StabChain::new(gens, &mut StdRng::seed_from_u64(seed))
+            .order()
+            .to_string()
+    }
+
+    fn twists(def: &PuzzleDef) -> Vec<Twist> {
+        (0..def.degree)
+            .flat_map(|cell| Move::all(def, cell))
+            .map(|m| m.twist(def))
+            .collect()
+    }
+
+    #[test]
+    fn orders_of_known_groups() {
+        let cycle: Vec<usize> = (0..8).collect();
+        let symmetric = [from_cycles(8, &[&[0, 1]]), from_cycles(8, &[&cycle])];
+        assert_eq!(order(&symmetric, 0), "40320");
+        let alternating: Vec<Permutation> = (2..8).map(|p| from_cycles(8, &[&[0, 1, p]])).collect();
+        assert_eq!(order(&alternating, 0), "20160");
+        let cycle: Vec<usize> = (0..11).collect();
+        let mathieu = [
+            from_cycles(11, &[&cycle]),
+            from_cycles(11, &[&[2, 6, 10, 7], &[3, 9, 4, 5]]),
+        ];
+        assert_eq!(order(&mathieu, 0), "7920");
+    }
+
+    #[test]
+    fn eleven_cell_group_order() {
+        let def = PuzzleDef::builtin();
+        assert_eq!(Analysis::new(&def).order.to_string(), ELEVEN_CELL_ORDER);
+        // the chain is built from random elements, so it must not depend on which
+        let action = StickerAction::new(&def, &twists(&def));
+        let gens: Vec<Permutation> = twists(&def).iter().map(|t| action.twist(t)).collect();
+        for seed in 1..4 {
+            assert_eq!(order(&gens, seed), ELEVEN_CELL_ORDER);
+        }
+    }
+
+    // proves the order: the chain is complete if every Schreier generator of every level
+    // sifts through the levels below it. takes minutes, so run it with `--ignored`
+    #[test]
+    #[ignore]
+    fn eleven_cell_chain_is_complete() {
+        let def = PuzzleDef::builtin();
+        let action = StickerAction::new(&def, &twists(&def));
+        let gens: Vec<Permutation> = twists(&def).iter().map(|t| action.twist(t)).collect();
+        let chain = StabChain::new(&gens, &mut StdRng::seed_from_u64(0));
+        for (i, level) in chain.levels.iter().enumerate() {
+            for &p in &level.orbit {
+                let u = level.inv_transversal[p].as_ref().unwrap().inverse();
+                for (g, _, _) in chain.gens.iter().filter(|&&(_, _, j)| j >= i) {
+                    let u_inv = level.inv_transversal[g.permute(p)].as_ref().unwrap();
+                    let (residue, _) = chain.sift_from(u.product(g).product(u_inv), i + 1);
+                    assert!(residue.is_identity());
+                }
+            }
+        }
+        assert_eq!(chain.order().to_string(), ELEVEN_CELL_ORDER);
+    }
+
+    #[test]
+    fn eleven_cell_orbits_and_parities() {
+        let def = Arc::new(PuzzleDef::builtin());
+        let analysis = Analysis::new(&def);
+
+        // the places the base pieces reach under the twists and the cell recenterings,
+        // which are symmetries of the puzzle, by their number of grips
+        let moved = |sig: u64, twist: &Twist| {
+            let piece = Piece::from_mask(sig, def.degree);
+            match piece.in_grip(twist.grip) {
+                true => piece.rotate(&twist.rot).sig,
+                false => sig,
+            }
+        };
+        let twists = twists(&def);
+        let mut all: Vec<u64> = def.base_pieces.iter().map(|p| p.sig).collect();
+        let mut k = 0;
+        while k < all.len() {
+            let sig = all[k];
+            let turned = twists.iter().map(|t| moved(sig, t));
+            let recentered =
+                (def.cell_recenter.iter()).map(|r| Piece::from_mask(sig, def.degree).rotate(r).sig);
+            for to in turned.chain(recentered) {
+                if !all.contains(&to) {
+                    all.push(to);
+                }
+            }
+            k += 1;
+        }
+        let mut positions: HashMap<usize, Vec<u64>> = HashMap::new();
+        for sig in all {
+            positions
+                .entry(sig.count_ones() as usize)
+                .or_default()
+                .push(sig);
+        }
+        for t in &analysis.types {
+            assert_eq!(t.pieces, positions[&t.grips].len());
+            // the core never moves; every other type is one orbit of pieces and of stickers
+            if t.grips == 1 {
+                assert!(t.position_orbits.iter().all(|&n| n == 1));
+            } else {
+                assert_eq!(t.position_orbits, [t.pieces]);
+                assert_eq!(t.sticker_orbits, [t.pieces * t.grips]);
+            }
+        }
+        assert_eq!(analysis.types.len(), positions.len());
+
+        // a type is always even if every twist moves its pieces by an even permutation
+        for t in &analysis.types {
+            let of_type = &positions[&t.grips];
+            let even = twists.iter().all(|twist| {
+                let perm = of_type
+                    .iter()
+                    .map(|&sig| {
+                        let to = moved(sig, twist);
+                        of_type.iter().position(|&s| s == to).unwrap()
+                    })
+                    .collect();
+                !Permutation::new(perm).is_odd()
+            });
+            assert_eq!(t.always_even, even);
+        }
+        assert!(analysis.linked_parities.is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+// Move history as a tree: undoing keeps the undone twists, and a new twist made after an
+// undo starts a branch next to the old line instead of replacing it.
+
+use crate::puzzle::twist::Twist;
+
+struct Node {
+    twist: Option<Twist>,
+    parent: usize,
+    children: Vec<usize>,
+    // the child that redo goes to
+    redo: Option<usize>,
+}
+
+pub struct History {
+    nodes: Vec<Node>,
+    current: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node {
+                twist: None,
+                parent: 0,
+                children: Vec::new(),
+                redo: None,
+            }],
+            current: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn push(&mut self, twist: &Twist) {
+        let existing = self.nodes[self.current]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| {
+                self.nodes[c]
+                    .twist
+                    .as_ref()
+                    .is_some_and(|t| t.grip == twist.grip && t.rot == twist.rot)
+            });
+        let child = existing.unwrap_or_else(|| {
+            self.nodes.push(Node {
+                twist: Some(twist.clone()),
+                parent: self.current,
+                children: Vec::new(),
+                redo: None,
+            });
+            let child = self.nodes.len() - 1;
+            self.nodes[self.current].children.push(child);
+            child
+        });
+        self.nodes[self.current].redo = Some(child);
+        self.current = child;
+    }
+
+    pub fn undo(&mut self) -> Option<Twist> {
+        let node = &self.nodes[self.current];
+        let twist = node.twist.clone()?;
+        let (child, parent) = (self.current, node.parent);
+        self.nodes[parent].redo = Some(child);
+        self.current = parent;
+        Some(twist)
+    }
+
+    pub fn redo(&mut self) -> Option<Twist> {
+        let child = self.nodes[self.current].redo?;
+        self.current = child;
+        self.nodes[child].twist.clone()
+    }
+
+    // the lines that redo can continue along, as (first twist, length, selected)
+    pub fn branches(&self) -> Vec<(&Twist, usize, bool)> {
+        let node = &self.nodes[self.current];
+        node.children
+            .iter()
+            .map(|&c| {
+                let mut len = 1;
+                let mut n = c;
+                while let Some(next) = self.nodes[n].redo {
+                    len += 1;
+                    n = next;
+                }
+                let twist = self.nodes[c]
+                    .twist
+                    .as_ref()
+                    .expect("only the root has no twist");
+                (twist, len, node.redo == Some(c))
+            })
+            .collect()
+    }
+
+    pub fn select_branch(&mut self, i: usize) {
+        let node = &mut self.nodes[self.current];
+        node.redo = node.children.get(i).copied().or(node.redo);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::perm::Permutation;
+
+    fn twist(grip: usize) -> Twist {
+        Twist {
+            grip,
+            rot: Permutation::new(vec![1, 2, 0]),
+        }
+    }
+
+    fn grip(twist: Option<Twist>) -> Option<usize> {
+        twist.map(|t| t.grip)
+    }
+
+    #[test]
+    fn a_twist_after_undo_starts_a_branch() {
+        let mut history = History::new();
+        history.push(&twist(0));
+        history.push(&twist(1));
+        history.push(&twist(2));
+        assert_eq!(grip(history.undo()), Some(2));
+        assert_eq!(grip(history.undo()), Some(1));
+        history.push(&twist(3));
+
+        // the new line is the one redo follows, and the old one is still there
+        assert_eq!(grip(history.undo()), Some(3));
+        let branches: Vec<(usize, usize, bool)> = history
+            .branches()
+            .into_iter()
+            .map(|(t, len, selected)| (t.grip, len, selected))
+            .collect();
+        assert_eq!(branches, [(1, 2, false), (3, 1, true)]);
+        assert_eq!(grip(history.redo()), Some(3));
+        assert_eq!(grip(history.redo()), None);
+
+        history.undo();
+        history.select_branch(0);
+        assert_eq!(grip(history.redo()), Some(1));
+        assert_eq!(grip(history.redo()), Some(2));
+        assert_eq!(grip(history.redo()), None);
+
+        // back at the root, undo has nothing left
+        for _ in 0..3 {
+            history.undo();
+        }
+        assert_eq!(grip(history.undo()), None);
+        assert_eq!(grip(history.redo()), Some(0));
+    }
+}
@@ -0,0 +1,178 @@
+// Solve logs: the puzzle name, the scramble and every twist made since, in twist notation.
+//
+//     puzzle: Alternative 11-Cell
+//     seed: 1234
+//     scramble: 3.7F 0.2V1' ...
+//     moves: 1.4E0 ...
+//
+// Long sequences may be split over several `scramble:` or `moves:` lines. A log with a
+// seed but no scramble twists gets the scramble generated from the seed.
+
+use std::{fmt, fs, io, path::Path, sync::Arc};
+
+use crate::puzzle::{
+    def::PuzzleDef,
+    notation,
+    state::{PuzzleState, SCRAMBLE_LENGTH},
+    twist::Twist,
+};
+
+#[derive(Debug)]
+pub enum LogError {
+    Io(io::Error),
+    Parse { line: usize, msg: String },
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogError::Io(e) => write!(f, "{e}"),
+            LogError::Parse { line: 0, msg } => write!(f, "{msg}"),
+            LogError::Parse { line, msg } => write!(f, "line {line}: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for LogError {}
+
+impl From<io::Error> for LogError {
+    fn from(e: io::Error) -> Self {
+        LogError::Io(e)
+    }
+}
+
+pub struct SolveLog {
+    pub puzzle: String,
+    pub seed: Option<u64>,
+    pub scramble: Vec<Twist>,
+    pub moves: Vec<Twist>,
+}
+
+impl SolveLog {
+    pub fn from_state(state: &PuzzleState) -> Self {
+        Self {
+            puzzle: state.def.name.clone(),
+            seed: state.scramble_seed,
+            scramble: state.scramble.clone(),
+            moves: state.twist_stack.clone(),
+        }
+    }
+
+    pub fn load(def: &PuzzleDef, path: impl AsRef<Path>) -> Result<Self, LogError> {
+        Self::parse(def, &fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, def: &PuzzleDef, path: impl AsRef<Path>) -> Result<(), LogError> {
+        Ok(fs::write(path, self.write(def))?)
+    }
+
+    pub fn parse(def: &PuzzleDef, src: &str) -> Result<Self, LogError> {
+        let mut log = Self {
+            puzzle: String::new(),
+            seed: None,
+            scramble: Vec::new(),
+            moves: Vec::new(),
+        };
+        for (i, line) in src.lines().enumerate() {
+            let error = |msg: String| LogError::Parse { line: i + 1, msg };
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((key, values)) = line.split_once(':') else {
+                return Err(error("expected `key: values`".to_string()));
+            };
+            match key.trim() {
+                "puzzle" => log.puzzle = values.trim().to_string(),
+                "seed" => {
+                    let seed = values.trim();
+                    log.seed = Some(
+                        seed.parse()
+                            .map_err(|_| error(format!("bad seed `{seed}`")))?,
+                    );
+                }
+                "scramble" => log.scramble.extend(
+                    notation::parse_sequence(def, values).map_err(|e| error(e.to_string()))?,
+                ),
+                "moves" => log.moves.extend(
+                    notation::parse_sequence(def, values).map_err(|e| error(e.to_string()))?,
+                ),
+                key => return Err(error(format!("unknown entry `{key}`"))),
+            }
+        }
+        if log.puzzle != def.name {
+            return Err(LogError::Parse {
+                line: 0,
+                msg: format!("log is for `{}`, not `{}`", log.puzzle, def.name),
+            });
+        }
+        if let Some(seed) = log.seed
+            && log.scramble.is_empty()
+        {
+            log.scramble = PuzzleState::scramble_twists(def, seed, SCRAMBLE_LENGTH);
+        }
+        Ok(log)
+    }
+
+    pub fn write(&self, def: &PuzzleDef) -> String {
+        let mut out = format!("puzzle: {}\n", self.puzzle);
+        if let Some(seed) = self.seed {
+            out += &format!("seed: {seed}\n");
+        }
+        for (key, twists) in [("scramble", &self.scramble), ("moves", &self.moves)] {
+            for chunk in twists.chunks(20) {
+                out += &format!("{key}: {}\n", notation::format_sequence(def, chunk));
+            }
+        }
+        out
+    }
+
+    // rebuilds the puzzle by applying the scramble and then every move in order
+    pub fn replay(&self, def: Arc<PuzzleDef>) -> PuzzleState {
+        let mut state = PuzzleState::new(def);
+        for twist in &self.scramble {
+            state.twist(twist);
+        }
+        state.scramble = self.scramble.clone();
+        state.scramble_seed = self.seed;
+        for twist in &self.moves {
+            state.twist_move(twist);
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_read_back_and_replay_to_the_same_pieces() {
+        let def = Arc::new(PuzzleDef::builtin());
+        let mut state = PuzzleState::new(def.clone());
+        state.scramble(7, 30);
+        for twist in PuzzleState::scramble_twists(&def, 8, 45) {
+            state.twist_move(&twist);
+        }
+
+        let log = SolveLog::parse(&def, &SolveLog::from_state(&state).write(&def)).unwrap();
+        assert_eq!(log.seed, Some(7));
+        assert_eq!(log.scramble.len(), 30);
+        assert_eq!(log.moves.len(), 45);
+        let replayed = log.replay(def);
+        assert_eq!(replayed.pieces, state.pieces);
+        assert_eq!(replayed.twist_stack.len(), 45);
+    }
+
+    #[test]
+    fn logs_of_other_puzzles_are_rejected() {
+        let def = PuzzleDef::builtin();
+        let other = PuzzleDef::named("57cell").unwrap();
+        let src = format!("puzzle: {}\nseed: 1\n", other.name);
+        assert!(matches!(
+            SolveLog::parse(&def, &src),
+            Err(LogError::Parse { line: 0, .. })
+        ));
+        assert!(SolveLog::parse(&other, &src).is_ok());
+    }
+}
@@ -0,0 +1,177 @@
+// Macros: named twist sequences, each recorded about a cell and face of its own and played
+// back about any other by conjugating every twist with the symmetry taking the one spot to
+// the other. Saved one to a line, as `<cell>.<face> <name>: <twists>`:
+//
+//     0.1 corner 3-cycle: 0.1F 2.1V0 0.1F' 2.1V0'
+//
+// Names may not contain `:` or `#`, which `clean_name` takes out. A line that doesn't read
+// is skipped, so one bad macro doesn't lose the others.
+
+use std::{fmt, fs, io, path::Path};
+
+use crate::puzzle::{def::PuzzleDef, notation, perm::Permutation, twist::Twist};
+
+#[derive(Debug)]
+pub enum MacroError {
+    Io(io::Error),
+    Parse { line: usize, msg: String },
+}
+
+impl fmt::Display for MacroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacroError::Io(e) => write!(f, "{e}"),
+            MacroError::Parse { line, msg } => write!(f, "line {line}: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for MacroError {}
+
+impl From<io::Error> for MacroError {
+    fn from(e: io::Error) -> Self {
+        MacroError::Io(e)
+    }
+}
+
+#[derive(Clone)]
+pub struct Macro {
+    pub name: String,
+    // the spot the twists were recorded about
+    pub cell: usize,
+    pub face: usize,
+    pub twists: Vec<Twist>,
+}
+
+impl Macro {
+    // the twists made about `cell` and `face` instead
+    pub fn relocate(&self, def: &PuzzleDef, cell: usize, face: usize) -> Vec<Twist> {
+        // takes the recorded spot to cell 0's face 1 and from there to the new one
+        let to = recenter(def, self.cell, self.face).product(&recenter(def, cell, face).inverse());
+        let back = to.inverse();
+        self.twists.iter().map(|t| t.conjugate(&back)).collect()
+    }
+}
+
+// as in `Move::twist`
+fn recenter(def: &PuzzleDef, cell: usize, face: usize) -> Permutation {
+    def.cell_recenter(cell).product(def.face_recenter(face))
+}
+
+pub fn load(
+    def: &PuzzleDef,
+    path: impl AsRef<Path>,
+) -> Result<(Vec<Macro>, Vec<MacroError>), MacroError> {
+    Ok(parse(def, &fs::read_to_string(path)?))
+}
+
+pub fn save(def: &PuzzleDef, macros: &[Macro], path: impl AsRef<Path>) -> Result<(), MacroError> {
+    Ok(fs::write(path, write(def, macros))?)
+}
+
+// takes out of a name the characters that would end it early when read back
+pub fn clean_name(name: &mut String) {
+    name.retain(|c| c != ':' && c != '#');
+}
+
+// the macros on the lines that read, and an error for each line that didn't
+pub fn parse(def: &PuzzleDef, src: &str) -> (Vec<Macro>, Vec<MacroError>) {
+    let mut macros = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in src.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        match parse_line(def, line) {
+            Ok(m) => macros.push(m),
+            Err(msg) => errors.push(MacroError::Parse { line: i + 1, msg }),
+        }
+    }
+    (macros, errors)
+}
+
+fn parse_line(def: &PuzzleDef, line: &str) -> Result<Macro, String> {
+    let Some((head, twists)) = line.split_once(':') else {
+        return Err("expected `<cell>.<face> <name>: <twists>`".to_string());
+    };
+    let (spot, name) = head.trim().split_once(' ').unwrap_or((head.trim(), ""));
+    let spot = spot
+        .split_once('.')
+        .and_then(|(c, f)| Some((c.parse().ok()?, f.parse().ok()?)));
+    let Some((cell, face)) = spot.filter(|&(c, f)| c < def.degree && def.face_grips.contains(&f))
+    else {
+        return Err(format!("bad cell and face `{}`", head.trim()));
+    };
+    Ok(Macro {
+        name: name.trim().to_string(),
+        cell,
+        face,
+        twists: notation::parse_sequence(def, twists).map_err(|e| e.to_string())?,
+    })
+}
+
+pub fn write(def: &PuzzleDef, macros: &[Macro]) -> String {
+    let mut out = String::new();
+    for m in macros {
+        let mut name = m.name.clone();
+        clean_name(&mut name);
+        out += &format!(
+            "{}.{} {}: {}\n",
+            m.cell,
+            m.face,
+            name.trim(),
+            notation::format_sequence(def, &m.twists)
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::notation::Move;
+
+    #[test]
+    fn relocating_gives_the_same_twists_at_another_spot() {
+        let def = PuzzleDef::builtin();
+        let (from, to) = ((2, def.face_grips[3]), (7, def.face_grips[5]));
+        let moves: Vec<Move> = Move::all(&def, from.0)
+            .filter(|m| m.face == from.1)
+            .collect();
+        let m = Macro {
+            name: "all".to_string(),
+            cell: from.0,
+            face: from.1,
+            twists: moves.iter().map(|m| m.twist(&def)).collect(),
+        };
+        for (moved, relocated) in moves.iter().zip(m.relocate(&def, to.0, to.1)) {
+            let expected = Move {
+                cell: to.0,
+                face: to.1,
+                ..*moved
+            }
+            .twist(&def);
+            assert_eq!(relocated.grip, expected.grip);
+            assert_eq!(relocated.rot, expected.rot);
+        }
+    }
+
+    #[test]
+    fn bad_lines_are_skipped() {
+        let def = PuzzleDef::builtin();
+        let mut name = "a: b # c".to_string();
+        clean_name(&mut name);
+        let m = Macro {
+            name,
+            cell: 0,
+            face: 1,
+            twists: notation::parse_sequence(&def, "0.1F 2.1F'").unwrap(),
+        };
+        let src = write(&def, &[m.clone(), m]);
+        let (read, errors) = parse(&def, &format!("0.1 bad: 0.1Z\n{src}"));
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].name, "a b  c");
+        assert!(matches!(errors[..], [MacroError::Parse { line: 1, .. }]));
+    }
+}
@@ -0,0 +1,97 @@
+// Move counts of the twists made on a puzzle, in the metrics solves are compared by:
+//
+// - twists: every twist counts 1, and so does every turn of the whole puzzle
+// - moves: turns of the whole puzzle don't count
+// - merged: nor do they, and twists of one grip in a row count once, or not at all when
+//   they cancel out
+//
+// Turning the whole puzzle only changes the view, so those turns never reach `twist_stack`
+// and are counted by the state as they are made. Undos aren't in any of the metrics, as an
+// undone twist leaves `twist_stack`; they are counted on their own too.
+
+use crate::puzzle::{perm::Permutation, state::PuzzleState};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct MoveCount {
+    pub twists: usize,
+    pub moves: usize,
+    pub merged: usize,
+    pub undos: usize,
+}
+
+impl MoveCount {
+    pub fn of(state: &PuzzleState) -> Self {
+        let mut count = MoveCount {
+            twists: state.twist_stack.len() + state.rotations,
+            moves: state.twist_stack.len(),
+            undos: state.undos,
+            ..Default::default()
+        };
+        // the grip of the current run of twists and their product
+        let mut run: Option<(usize, Permutation)> = None;
+        let mut close = |run: Option<(usize, Permutation)>| {
+            if run.is_some_and(|(_, rot)| !rot.is_identity()) {
+                count.merged += 1;
+            }
+        };
+        for twist in &state.twist_stack {
+            match &mut run {
+                Some((grip, rot)) if *grip == twist.grip => *rot = rot.product(&twist.rot),
+                _ => close(run.replace((twist.grip, twist.rot.clone()))),
+            }
+        }
+        close(run);
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::puzzle::{def::PuzzleDef, notation};
+
+    #[test]
+    fn counts_in_each_metric() {
+        let def = Arc::new(PuzzleDef::builtin());
+        // three face twists come to nothing
+        assert_eq!(def.face_rot(false).order(), 3);
+        // twists, turns of the whole puzzle, undos at the end, and the counts they give
+        let cases = [
+            ("", 0, 0, (0, 0, 0)),
+            ("0.1F", 0, 0, (1, 1, 1)),
+            // a run of twists of one cell counts once, and not at all when it comes to nothing
+            ("0.1F 0.1F", 0, 0, (2, 2, 1)),
+            ("0.1F 0.1F'", 0, 0, (2, 2, 0)),
+            ("0.1F 0.1F 0.1F", 0, 0, (3, 3, 0)),
+            ("0.1F 0.2F 0.2F' 0.1F'", 0, 0, (4, 4, 0)),
+            ("0.1F 2.1F 0.1F", 0, 0, (3, 3, 3)),
+            // runs only merge when next to each other, even with one between them cancelled
+            ("0.1F 2.1F 2.1F' 0.1F", 0, 0, (4, 4, 2)),
+            // turns of the whole puzzle are twists but not moves
+            ("0.1F", 2, 0, (3, 1, 1)),
+            ("0.1F 2.1F 3.1F", 0, 2, (1, 1, 1)),
+        ];
+        for (twists, rotations, undos, (t, m, merged)) in cases {
+            let mut state = PuzzleState::new(def.clone());
+            for twist in notation::parse_sequence(&def, twists).unwrap() {
+                state.twist_move(&twist);
+            }
+            for _ in 0..undos {
+                state.undo();
+            }
+            state.rotations = rotations;
+            assert_eq!(
+                MoveCount::of(&state),
+                MoveCount {
+                    twists: t,
+                    moves: m,
+                    merged,
+                    undos,
+                },
+                "{twists}"
+            );
+        }
+    }
+}
@@ -1,7 +1,21 @@
+pub mod def;
+#[cfg(feature = "gui")]
+pub mod export;
+pub mod finder;
+pub mod group;
+pub mod history;
+pub mod log;
+pub mod macros;
+pub mod metrics;
+pub mod notation;
 pub mod perm;
 pub mod piece;
-pub mod setup11c;
+pub mod polytope;
+pub mod solver;
 pub mod state;
+pub mod timer;
 pub mod twist;
+#[cfg(feature = "gui")]
 pub mod view;
+#[cfg(feature = "gui")]
 pub mod viewsettings;
@@ -0,0 +1,261 @@
+// Twist notation: `<cell>.<face><kind>`, e.g. `3.7F`, `3.7V1'` or `0.2E0`.
+//
+// `F` turns the face, `V<k>` turns about the k-th vertex of the face and `E<i>` flips
+// the edge from vertex i to vertex i + 1 (vertex order as in `face_recenter`'s frame).
+// A trailing `'` reverses the direction; edge flips have no direction. Twists that have
+// no such name are written as the grip followed by the raw permutation, e.g. `3[0,2,1,...]`;
+// these are read back only if the cell can make that twist.
+
+use std::fmt;
+
+use crate::puzzle::{def::PuzzleDef, perm::Permutation, twist::Twist};
+
+#[derive(Debug)]
+pub struct NotationError {
+    pub token: String,
+    pub msg: String,
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`: {}", self.token, self.msg)
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TwistKind {
+    Face { ccw: bool },
+    Vertex { vertex: usize, ccw: bool },
+    Edge { edge: usize },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Move {
+    pub cell: usize,
+    pub face: usize,
+    pub kind: TwistKind,
+}
+
+impl Move {
+    pub fn twist(&self, def: &PuzzleDef) -> Twist {
+        let align = |k: usize| def.face_rot(false).exp(k);
+        let rot = match self.kind {
+            TwistKind::Face { ccw } => def.face_rot(ccw).clone(),
+            TwistKind::Vertex { vertex, ccw } => def.vertex_rot(ccw).conjugate(&align(vertex)),
+            TwistKind::Edge { edge } => def.edge_rot().conjugate(&align(edge + 1)),
+        };
+        let recenter = def
+            .cell_recenter(self.cell)
+            .product(def.face_recenter(self.face));
+        Twist {
+            grip: self.cell,
+            rot: rot.conjugate(&recenter),
+        }
+    }
+
+    // every named twist of a cell, in canonical order
+    pub fn all(def: &PuzzleDef, cell: usize) -> impl Iterator<Item = Move> {
+        let n = face_size(def);
+        def.face_grips.iter().flat_map(move |&face| {
+            let kinds =
+                [true, false]
+                    .map(|ccw| TwistKind::Face { ccw })
+                    .into_iter()
+                    .chain((0..n).flat_map(|vertex| {
+                        [true, false].map(|ccw| TwistKind::Vertex { vertex, ccw })
+                    }))
+                    .chain((0..n).map(|edge| TwistKind::Edge { edge }));
+            kinds.map(move |kind| Move { cell, face, kind })
+        })
+    }
+
+    pub fn from_twist(def: &PuzzleDef, twist: &Twist) -> Option<Move> {
+        Move::all(def, twist.grip).find(|m| m.twist(def).rot == twist.rot)
+    }
+
+    pub fn parse(def: &PuzzleDef, token: &str) -> Result<Move, NotationError> {
+        let error = |msg: &str| NotationError {
+            token: token.to_string(),
+            msg: msg.to_string(),
+        };
+
+        let (body, reverse) = match token.strip_suffix('\'') {
+            Some(body) => (body, true),
+            None => (token, false),
+        };
+        let (cell, rest) = body
+            .split_once('.')
+            .ok_or_else(|| error("expected `<cell>.<face><kind>`"))?;
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(|| error("missing twist kind"))?;
+        let (face, rest) = rest.split_at(split);
+        let mut chars = rest.chars();
+        let kind = chars.next();
+        let index = chars.as_str();
+
+        let cell: usize = cell.parse().map_err(|_| error("bad cell number"))?;
+        let face: usize = face.parse().map_err(|_| error("bad face number"))?;
+        if cell >= def.degree {
+            return Err(error("no such cell"));
+        }
+        if !def.face_grips.contains(&face) {
+            return Err(error("no such face"));
+        }
+        let index = match index {
+            "" => None,
+            index => match index.parse::<usize>() {
+                Ok(i) if i < face_size(def) => Some(i),
+                _ => return Err(error("bad vertex or edge number")),
+            },
+        };
+
+        let kind = match (kind, index, reverse) {
+            (Some('F'), None, _) => TwistKind::Face { ccw: !reverse },
+            (Some('V'), Some(vertex), _) => TwistKind::Vertex {
+                vertex,
+                ccw: !reverse,
+            },
+            (Some('E'), Some(edge), false) => TwistKind::Edge { edge },
+            (Some('E'), Some(_), true) => return Err(error("edge twists have no direction")),
+            (Some('V' | 'E'), None, _) => return Err(error("missing vertex or edge number")),
+            (Some('F'), Some(_), _) => return Err(error("face twists take no number")),
+            _ => return Err(error("twist kind must be F, V or E")),
+        };
+        Ok(Move { cell, face, kind })
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.cell, self.face)?;
+        match self.kind {
+            TwistKind::Face { ccw } => write!(f, "F{}", if ccw { "" } else { "'" }),
+            TwistKind::Vertex { vertex, ccw } => {
+                write!(f, "V{vertex}{}", if ccw { "" } else { "'" })
+            }
+            TwistKind::Edge { edge } => write!(f, "E{edge}"),
+        }
+    }
+}
+
+pub fn parse_twist(def: &PuzzleDef, token: &str) -> Result<Twist, NotationError> {
+    match token.split_once('[') {
+        Some((grip, perm)) => parse_raw(def, token, grip, perm),
+        None => Ok(Move::parse(def, token)?.twist(def)),
+    }
+}
+
+pub fn parse_sequence(def: &PuzzleDef, src: &str) -> Result<Vec<Twist>, NotationError> {
+    src.split_whitespace()
+        .map(|token| parse_twist(def, token))
+        .collect()
+}
+
+// the twists of a cell, one for each distinct rotation, in canonical order
+pub fn cell_twists(def: &PuzzleDef, cell: usize) -> Vec<Twist> {
+    let mut twists: Vec<Twist> = Vec::new();
+    for m in Move::all(def, cell) {
+        let twist = m.twist(def);
+        if !twists.iter().any(|t| t.rot == twist.rot) {
+            twists.push(twist);
+        }
+    }
+    twists
+}
+
+pub fn format_twist(def: &PuzzleDef, twist: &Twist) -> String {
+    match Move::from_twist(def, twist) {
+        Some(m) => m.to_string(),
+        None => {
+            let perm: Vec<String> = twist.rot.perm.iter().map(|i| i.to_string()).collect();
+            format!("{}[{}]", twist.grip, perm.join(","))
+        }
+    }
+}
+
+pub fn format_sequence(def: &PuzzleDef, twists: &[Twist]) -> String {
+    let names: Vec<String> = twists.iter().map(|t| format_twist(def, t)).collect();
+    names.join(" ")
+}
+
+fn parse_raw(def: &PuzzleDef, token: &str, grip: &str, perm: &str) -> Result<Twist, NotationError> {
+    let error = |msg: &str| NotationError {
+        token: token.to_string(),
+        msg: msg.to_string(),
+    };
+    let grip: usize = grip.parse().map_err(|_| error("bad grip number"))?;
+    let perm = perm
+        .strip_suffix(']')
+        .ok_or_else(|| error("missing `]`"))?
+        .split(',')
+        .map(|i| i.parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| error("bad permutation"))?;
+
+    let mut seen = vec![false; def.degree];
+    if grip >= def.degree || perm.len() != def.degree {
+        return Err(error("wrong degree"));
+    }
+    for &i in &perm {
+        if i >= def.degree || seen[i] {
+            return Err(error("not a permutation"));
+        }
+        seen[i] = true;
+    }
+    // only a twist the cell can make, so nothing read in can put the puzzle in a state no
+    // twists lead to
+    let rot = Permutation::new(perm);
+    if !cell_twists(def, grip).iter().any(|t| t.rot == rot) {
+        return Err(error("not a twist of this cell"));
+    }
+    Ok(Twist { grip, rot })
+}
+
+// number of vertices of a face, i.e. the order of `face_rot`
+fn face_size(def: &PuzzleDef) -> usize {
+    def.face_rot(false).order() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::state::PuzzleState;
+
+    #[test]
+    fn names_read_back_as_written() {
+        for name in PuzzleDef::builtin_names() {
+            let def = PuzzleDef::named(name).unwrap();
+            for cell in 0..def.degree {
+                for m in Move::all(&def, cell) {
+                    assert_eq!(Move::parse(&def, &m.to_string()).unwrap(), m);
+                }
+            }
+
+            let twists = PuzzleState::scramble_twists(&def, 3, 100);
+            let written = format_sequence(&def, &twists);
+            let read = parse_sequence(&def, &written).unwrap();
+            assert_eq!(read.len(), twists.len());
+            assert!((twists.iter().zip(&read)).all(|(a, b)| a.grip == b.grip && a.rot == b.rot));
+            assert_eq!(format_sequence(&def, &read), written);
+        }
+    }
+
+    #[test]
+    fn raw_twists_must_be_twists_of_their_cell() {
+        let def = PuzzleDef::builtin();
+        let raw = |perm: &[usize]| {
+            let perm: Vec<String> = perm.iter().map(|i| i.to_string()).collect();
+            format!("0[{}]", perm.join(","))
+        };
+        let twist = &cell_twists(&def, 0)[0];
+        assert!(parse_twist(&def, &raw(&twist.rot.perm)).is_ok());
+
+        // swapping two other cells fixes the grip but is no twist of it
+        let mut swap: Vec<usize> = (0..def.degree).collect();
+        swap.swap(1, 2);
+        assert!(parse_twist(&def, &raw(&swap)).is_err());
+    }
+}
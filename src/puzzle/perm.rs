@@ -1,4 +1,8 @@
-#[derive(Clone)]
+use std::fmt;
+
+// `perm[i]` is the image of i. Products compose left to right: `a.product(&b)` applies a,
+// then b.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Permutation {
     pub perm: Vec<usize>,
     pub deg: usize,
@@ -14,6 +18,10 @@ impl Permutation {
         Self::new((0..degree).collect())
     }
 
+    pub fn is_identity(&self) -> bool {
+        self.perm.iter().enumerate().all(|(i, &j)| i == j)
+    }
+
     pub fn inverse(&self) -> Self {
         let mut inv = Self {
             perm: vec![0; self.deg],
@@ -26,24 +34,87 @@ impl Permutation {
     }
 
     pub fn product(&self, other: &Self) -> Self {
-        let mut prod = vec![0; self.deg];
-        for i in 0..self.deg {
-            prod[i] = other.perm[self.perm[i]];
-        }
-        Self::new(prod)
+        Self::new(self.perm.iter().map(|&i| other.perm[i]).collect())
     }
 
     pub fn exp(&self, n: usize) -> Self {
         let mut e = Permutation::identity(self.deg);
-        for i in 0..self.deg {
-            for _ in 0..n {
-                e.perm[i] = self.perm[e.perm[i]];
+        for cycle in self.cycles() {
+            let len = cycle.len();
+            for (i, &p) in cycle.iter().enumerate() {
+                e.perm[p] = cycle[(i + n) % len];
             }
         }
         e
     }
 
+    // [setup: self] = setup self setup⁻¹, applied in that order
+    pub fn conjugate(&self, setup: &Self) -> Self {
+        setup.product(self).product(&setup.inverse())
+    }
+
+    // [self, other] = self other self⁻¹ other⁻¹, applied in that order
+    pub fn commutator(&self, other: &Self) -> Self {
+        self.product(other)
+            .product(&self.inverse())
+            .product(&other.inverse())
+    }
+
+    // cycles of length at least 2, each starting at its smallest element
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        let mut seen = vec![false; self.deg];
+        let mut cycles = Vec::new();
+        for start in 0..self.deg {
+            if seen[start] || self.perm[start] == start {
+                continue;
+            }
+            let mut cycle = Vec::new();
+            let mut i = start;
+            while !seen[i] {
+                seen[i] = true;
+                cycle.push(i);
+                i = self.perm[i];
+            }
+            cycles.push(cycle);
+        }
+        cycles
+    }
+
+    // the lcm of the cycle lengths
+    pub fn order(&self) -> u128 {
+        self.cycles().iter().fold(1, |order, cycle| {
+            let len = cycle.len() as u128;
+            order / gcd(order, len) * len
+        })
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.cycles().iter().filter(|c| c.len() % 2 == 0).count() % 2 == 1
+    }
+
+    pub fn sign(&self) -> i32 {
+        if self.is_odd() { -1 } else { 1 }
+    }
+
     pub fn permute(&self, num: usize) -> usize {
         self.perm[num]
     }
 }
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+impl fmt::Display for Permutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cycles = self.cycles();
+        if cycles.is_empty() {
+            return write!(f, "()");
+        }
+        for cycle in cycles {
+            let cycle: Vec<String> = cycle.iter().map(|i| i.to_string()).collect();
+            write!(f, "({})", cycle.join(" "))?;
+        }
+        Ok(())
+    }
+}
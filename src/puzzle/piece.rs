@@ -1,49 +1,99 @@
 use crate::puzzle::perm::Permutation;
 
-#[derive(Clone)]
+// grips are bits of a u64 and attitudes fixed arrays, so pieces are `Copy` and twisting
+// never allocates
+pub const MAX_DEGREE: usize = 64;
+
+// the bitmask of the grips with a 1 in `sig`
+pub fn mask(sig: &[u8]) -> u64 {
+    sig.iter()
+        .enumerate()
+        .filter(|&(_, &s)| s == 1)
+        .fold(0, |m, (i, _)| m | 1 << i)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct Piece {
-    pub sig: Vec<u8>,
-    pub att: Permutation,
+    pub sig: u64,
+    pub att: [u8; MAX_DEGREE],
+    deg: u8,
 }
 
 impl Piece {
-    pub fn new(sig: Vec<u8>) -> Self {
-        let deg = sig.len();
+    pub fn new(sig: &[u8]) -> Self {
+        Self::from_mask(mask(sig), sig.len())
+    }
+
+    pub fn from_mask(sig: u64, degree: usize) -> Self {
+        let mut att = [0; MAX_DEGREE];
+        for (i, a) in att.iter_mut().enumerate() {
+            *a = i as u8;
+        }
         Self {
             sig,
-            att: Permutation::identity(deg),
+            att,
+            deg: degree as u8,
         }
     }
 
     pub fn degree(&self) -> usize {
-        self.att.deg
+        self.deg as usize
     }
 
     pub fn grip_state(&self, grip: usize) -> u8 {
-        self.sig[grip]
+        (self.sig >> grip & 1) as u8
+    }
+
+    pub fn in_grip(&self, grip: usize) -> bool {
+        self.sig >> grip & 1 == 1
+    }
+
+    pub fn attitude(&self) -> Permutation {
+        Permutation::new(
+            self.att[..self.degree()]
+                .iter()
+                .map(|&a| a as usize)
+                .collect(),
+        )
     }
 
     pub fn rotate(&self, rot: &Permutation) -> Self {
-        let mut new_sig = vec![0; self.degree()];
-        for i in 0..self.degree() {
-            new_sig[rot.permute(i)] = self.sig[i];
+        let mut new = *self;
+        new.rotate_by(&table(rot));
+        new
+    }
+
+    // `rot` as made by `table`, so that twisting many pieces converts it only once
+    pub fn rotate_by(&mut self, rot: &[u8; MAX_DEGREE]) {
+        let mut sig = 0;
+        for i in bits(self.sig) {
+            sig |= 1 << rot[i];
         }
-        Self {
-            att: self.att.product(&rot),
-            sig: new_sig,
+        self.sig = sig;
+        for a in &mut self.att[..self.deg as usize] {
+            *a = rot[*a as usize % MAX_DEGREE];
         }
     }
 
+    // att is a bijection, so it fixes the grips of the piece if it maps them into themselves
     pub fn is_solved(&self) -> bool {
-        for i in 0..self.degree() {
-            if self.sig[i] != self.sig[self.att.permute(i)] {
-                return false;
-            }
-        }
-        true
+        bits(self.sig).all(|i| self.sig >> self.att[i] & 1 == 1)
     }
+}
 
-    pub fn overlaps(&self, other: &Self) -> bool {
-        self.sig == other.sig
+pub fn table(rot: &Permutation) -> [u8; MAX_DEGREE] {
+    let mut table = [0; MAX_DEGREE];
+    for (t, &p) in table.iter_mut().zip(&rot.perm) {
+        *t = p as u8;
     }
+    table
+}
+
+// the indices of the set bits of `mask`
+pub fn bits(mut mask: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        let i = mask.trailing_zeros() as usize;
+        mask &= mask.wrapping_sub(1);
+        (i < 64).then_some(i)
+    })
 }
@@ -0,0 +1,411 @@
+// Regular abstract 4-polytopes given as Coxeter-style presentations: the reflections ρ0..ρ3
+// of a base flag (vertex, edge, face, cell), the Schläfli symbol {p, q, r} giving the orders
+// of ρ0ρ1, ρ1ρ2 and ρ2ρ3, and extra relations that fold the universal polytope into a
+// finite one. The 11-cell, for instance, is {3, 5, 3} with (ρ0ρ1ρ2)^5 = (ρ1ρ2ρ3)^5 = 1.
+//
+// Todd-Coxeter coset enumeration over the stabiliser of the base cell, <ρ0, ρ1, ρ2>, gives
+// the action of the reflections on the cells, numbered breadth first from the base cell
+// (so cell 1 is the one across the base face). The tables of a puzzle definition follow:
+//
+//   face_rot ccw = ρ0ρ1, turning the base face about its centre
+//   vertex_rot ccw = ρ1ρ2, turning the base cell about the base vertex
+//   edge_rot = ρ0ρ2, flipping the base cell over the base edge
+//
+// where a product applies its left factor first, as `Permutation::product` does.
+
+use std::fmt;
+
+use crate::puzzle::{
+    perm::Permutation,
+    piece::{MAX_DEGREE, Piece},
+};
+
+// coset tables are abandoned past this size
+const MAX_COSETS: usize = 1 << 20;
+
+#[derive(Debug)]
+pub enum PolytopeError {
+    TooManyCosets,
+    TooManyCells(usize),
+}
+
+impl fmt::Display for PolytopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolytopeError::TooManyCosets => write!(
+                f,
+                "coset enumeration passed {MAX_COSETS} cosets; is the polytope finite?"
+            ),
+            PolytopeError::TooManyCells(n) => {
+                write!(
+                    f,
+                    "the polytope has {n} cells, at most {MAX_DEGREE} are supported"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolytopeError {}
+
+pub struct Polytope {
+    pub schlafli: [usize; 3],
+    // words in the reflections, each equal to the identity
+    pub relations: Vec<Vec<usize>>,
+}
+
+// everything a puzzle definition needs besides its geometry
+pub struct Tables {
+    pub degree: usize,
+    pub base_pieces: Vec<Piece>,
+    pub generators: Vec<Permutation>,
+    pub cell_recenter: Vec<Permutation>,
+    pub face_recenter: Vec<Permutation>,
+    pub face_rot: [Permutation; 2],
+    pub vertex_rot: [Permutation; 2],
+    pub edge_rot: Permutation,
+}
+
+impl Polytope {
+    // the Coxeter relations of the Schläfli symbol followed by the extra ones
+    fn all_relations(&self) -> Vec<Vec<usize>> {
+        let [p, q, r] = self.schlafli;
+        let power = |a: usize, b: usize, n: usize| [a, b].repeat(n);
+        let mut relations = vec![
+            power(0, 1, p),
+            power(1, 2, q),
+            power(2, 3, r),
+            power(0, 2, 2),
+            power(0, 3, 2),
+            power(1, 3, 2),
+        ];
+        relations.extend(self.relations.iter().cloned());
+        relations
+    }
+
+    // the permutations of the cells made by ρ0..ρ3
+    pub fn reflections(&self) -> Result<[Permutation; 4], PolytopeError> {
+        let table = CosetTable::enumerate(&self.all_relations(), &[0, 1, 2])?;
+        if table.len() > MAX_DEGREE {
+            return Err(PolytopeError::TooManyCells(table.len()));
+        }
+        Ok([0, 1, 2, 3].map(|g| Permutation::new(table.iter().map(|row| row[g]).collect())))
+    }
+
+    pub fn tables(&self) -> Result<Tables, PolytopeError> {
+        let rho = self.reflections()?;
+        let degree = rho[0].perm.len();
+
+        // the symmetries taking each cell to the base cell, from the ones that reach it
+        let cell_recenter = reach(&rho, 0, degree)
+            .into_iter()
+            .map(|p| p.map_or(Permutation::identity(degree), |p| p.inverse()))
+            .collect();
+        // and those of the base cell taking each of its faces to face 1
+        let face_recenter = reach(&rho[..3], 1, degree)
+            .into_iter()
+            .map(|p| p.map_or(Permutation::identity(degree), |p| p.inverse()))
+            .collect();
+
+        // a piece for the base cell, face, edge and vertex: the cells around each
+        let around = |gens: &[Permutation]| {
+            let mut sig = vec![0; degree];
+            for (cell, p) in reach(gens, 0, degree).iter().enumerate() {
+                if p.is_some() {
+                    sig[cell] = 1;
+                }
+            }
+            Piece::new(&sig)
+        };
+        let base_pieces = vec![
+            around(&[]),
+            around(&rho[3..]),
+            around(&rho[2..]),
+            around(&rho[1..]),
+        ];
+
+        let face_ccw = rho[0].product(&rho[1]);
+        let vertex_ccw = rho[1].product(&rho[2]);
+        Ok(Tables {
+            degree,
+            base_pieces,
+            generators: rho.to_vec(),
+            cell_recenter,
+            face_recenter,
+            face_rot: [face_ccw.inverse(), face_ccw],
+            vertex_rot: [vertex_ccw.inverse(), vertex_ccw],
+            edge_rot: rho[0].product(&rho[2]),
+        })
+    }
+}
+
+// for every cell, a product of `gens` taking `start` to it, if there is one
+fn reach(gens: &[Permutation], start: usize, degree: usize) -> Vec<Option<Permutation>> {
+    let mut found = vec![None; degree];
+    found[start] = Some(Permutation::identity(degree));
+    let mut queue = vec![start];
+    let mut k = 0;
+    while k < queue.len() {
+        let cell = queue[k];
+        for g in gens {
+            let next = g.permute(cell);
+            if found[next].is_none() {
+                found[next] = found[cell].as_ref().map(|p: &Permutation| p.product(g));
+                queue.push(next);
+            }
+        }
+        k += 1;
+    }
+    found
+}
+
+const NONE: usize = usize::MAX;
+
+// a Todd-Coxeter coset table for a group generated by four involutions, so that c·g = d
+// exactly when d·g = c; cosets found equal are merged through `parent`
+struct CosetTable {
+    table: Vec<[usize; 4]>,
+    parent: Vec<usize>,
+}
+
+impl CosetTable {
+    // the action on the cosets of the subgroup generated by `subgroup`, in standard
+    // breadth-first order
+    fn enumerate(
+        relations: &[Vec<usize>],
+        subgroup: &[usize],
+    ) -> Result<Vec<[usize; 4]>, PolytopeError> {
+        let mut t = CosetTable {
+            table: vec![[NONE; 4]],
+            parent: vec![0],
+        };
+        for &h in subgroup {
+            t.scan_and_fill(0, &[h])?;
+        }
+        let mut c = 0;
+        while c < t.table.len() {
+            for relation in relations {
+                if !t.alive(c) {
+                    break;
+                }
+                t.scan_and_fill(c, relation)?;
+            }
+            if t.alive(c) {
+                for g in 0..4 {
+                    if t.get(c, g).is_none() {
+                        t.define(c, g)?;
+                    }
+                }
+            }
+            c += 1;
+        }
+        Ok(t.standardize())
+    }
+
+    fn alive(&self, c: usize) -> bool {
+        self.parent[c] == c
+    }
+
+    fn rep(&mut self, mut c: usize) -> usize {
+        while self.parent[c] != c {
+            self.parent[c] = self.parent[self.parent[c]];
+            c = self.parent[c];
+        }
+        c
+    }
+
+    fn get(&mut self, c: usize, g: usize) -> Option<usize> {
+        let d = self.table[c][g];
+        (d != NONE).then(|| self.rep(d))
+    }
+
+    fn define(&mut self, c: usize, g: usize) -> Result<usize, PolytopeError> {
+        if self.table.len() >= MAX_COSETS {
+            return Err(PolytopeError::TooManyCosets);
+        }
+        let d = self.table.len();
+        self.table.push([NONE; 4]);
+        self.parent.push(d);
+        self.table[c][g] = d;
+        self.table[d][g] = c;
+        Ok(d)
+    }
+
+    // records c·g = d, queueing any cosets this shows to be equal
+    fn set(&mut self, c: usize, g: usize, d: usize, merges: &mut Vec<(usize, usize)>) {
+        match self.get(c, g) {
+            Some(e) if e != d => merges.push((e, d)),
+            _ => self.table[c][g] = d,
+        }
+        match self.get(d, g) {
+            Some(e) if e != c => merges.push((e, c)),
+            _ => self.table[d][g] = c,
+        }
+    }
+
+    fn coincidence(&mut self, a: usize, b: usize) {
+        let mut merges = vec![(a, b)];
+        while let Some((a, b)) = merges.pop() {
+            let (a, b) = (self.rep(a), self.rep(b));
+            if a == b {
+                continue;
+            }
+            let (keep, kill) = (a.min(b), a.max(b));
+            self.parent[kill] = keep;
+            for g in 0..4 {
+                if self.table[kill][g] != NONE {
+                    let d = self.rep(self.table[kill][g]);
+                    self.set(keep, g, d, &mut merges);
+                }
+            }
+        }
+    }
+
+    // makes c·word = c hold, defining new cosets where the word can't be traced
+    fn scan_and_fill(&mut self, c: usize, word: &[usize]) -> Result<(), PolytopeError> {
+        loop {
+            let (mut f, mut i) = (c, 0);
+            let (mut b, mut j) = (c, word.len());
+            while i < j
+                && let Some(d) = self.get(f, word[i])
+            {
+                f = d;
+                i += 1;
+            }
+            if i == j {
+                if f != b {
+                    self.coincidence(f, b);
+                }
+                return Ok(());
+            }
+            // the generators are involutions, so tracing back uses the same letters
+            while j > i
+                && let Some(d) = self.get(b, word[j - 1])
+            {
+                b = d;
+                j -= 1;
+            }
+            if j == i {
+                self.coincidence(f, b);
+                return Ok(());
+            }
+            if j == i + 1 {
+                self.set(f, word[i], b, &mut Vec::new());
+                return Ok(());
+            }
+            self.define(f, word[i])?;
+        }
+    }
+
+    // the live cosets renumbered in breadth-first order from coset 0
+    fn standardize(mut self) -> Vec<[usize; 4]> {
+        let mut number = vec![NONE; self.table.len()];
+        let mut order = vec![0];
+        number[0] = 0;
+        let mut k = 0;
+        while k < order.len() {
+            let c = order[k];
+            for g in 0..4 {
+                let d = self.get(c, g).expect("coset table is complete");
+                if number[d] == NONE {
+                    number[d] = order.len();
+                    order.push(d);
+                }
+            }
+            k += 1;
+        }
+        order
+            .iter()
+            .map(|&c| [0, 1, 2, 3].map(|g| number[self.get(c, g).unwrap()]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::{def::PuzzleDef, piece};
+
+    fn polytope(schlafli: [usize; 3]) -> Polytope {
+        Polytope {
+            schlafli,
+            relations: vec![[0, 1, 2].repeat(5), [1, 2, 3].repeat(5)],
+        }
+    }
+
+    // the numbering of cells taking each of `from` to the one in `to` alongside it, found
+    // from cells 0 and 1 being the base cell and the one across its base face in both
+    fn relabelling(from: &[&Permutation], to: &[&Permutation]) -> Option<Vec<usize>> {
+        let degree = from[0].deg;
+        let mut label = vec![None; degree];
+        let mut queue = vec![0, 1];
+        label[0] = Some(0);
+        label[1] = Some(1);
+        while let Some(cell) = queue.pop() {
+            for (f, t) in from.iter().zip(to) {
+                let image = t.permute(label[cell]?);
+                match label[f.permute(cell)] {
+                    Some(l) if l != image => return None,
+                    Some(_) => (),
+                    None => {
+                        label[f.permute(cell)] = Some(image);
+                        queue.push(f.permute(cell));
+                    }
+                }
+            }
+        }
+        label.into_iter().collect()
+    }
+
+    #[test]
+    fn eleven_cell_tables_match_the_definition() {
+        let def = PuzzleDef::builtin();
+        let tables = polytope([3, 5, 3]).tables().unwrap();
+        assert_eq!(tables.degree, def.degree);
+
+        let derived = [
+            &tables.face_rot[0],
+            &tables.face_rot[1],
+            &tables.vertex_rot[0],
+            &tables.vertex_rot[1],
+            &tables.edge_rot,
+        ];
+        let given = [
+            &def.face_rot[0],
+            &def.face_rot[1],
+            &def.vertex_rot[0],
+            &def.vertex_rot[1],
+            &def.edge_rot,
+        ];
+        let label = relabelling(&derived, &given).expect("tables agree up to numbering");
+        let mut seen = label.clone();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), def.degree);
+
+        let relabel = |p: &Piece| {
+            let mut sig = vec![0; def.degree];
+            for cell in piece::bits(p.sig) {
+                sig[label[cell]] = 1;
+            }
+            Piece::new(&sig)
+        };
+        let base: Vec<Piece> = tables.base_pieces.iter().map(relabel).collect();
+        assert_eq!(base, def.base_pieces);
+        // the symmetries may differ, but must take each cell to the base cell all the same
+        for (cell, &given) in label.iter().enumerate() {
+            assert_eq!(tables.cell_recenter[cell].permute(cell), 0);
+            assert_eq!(def.cell_recenter[given].permute(given), 0);
+        }
+    }
+
+    #[test]
+    fn fifty_seven_cell_has_57_cells() {
+        let tables = polytope([5, 3, 5]).tables().unwrap();
+        assert_eq!(tables.degree, 57);
+        assert_eq!(PuzzleDef::named("57cell").unwrap().degree, 57);
+        // a base piece of each type, all of them around the base cell
+        assert_eq!(tables.base_pieces.len(), 4);
+        assert!(tables.base_pieces.iter().all(|p| p.in_grip(0)));
+    }
+}
@@ -0,0 +1,948 @@
+// A step-by-step solver, for beginners who can't finish a scramble and as a reference to
+// compare solutions against. The pieces are placed a type at a time, those in the fewest
+// cells first. Each stage draws on a pool of short algorithms built around cell 0: single
+// twists, commutators [x, y] of twists of cells 0 and 1 and commutators [[x, y], z] of
+// those with any twist. It keeps the ones that leave the types already placed alone, adds
+// the cycles undone by others turned so that they turn pieces in place, moves them anywhere
+// on the puzzle with its symmetries and setup twists and greedily applies whichever puts
+// the most stickers right. Solutions are long, but every step is one short algorithm.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, slice,
+};
+
+use crate::puzzle::{
+    def::PuzzleDef,
+    notation,
+    perm::Permutation,
+    piece::{self, MAX_DEGREE, Piece, bits},
+    state::PuzzleState,
+    twist::Twist,
+};
+
+// how many of the smallest commutators [x, y] are nested in a second one
+const NESTED: usize = 40;
+// the most pieces of a cycle combined with another to turn them in place
+const CYCLE: usize = 3;
+// and the most it may leave turned
+const TURNED: usize = 2;
+// how long those chains get, and how many pieces they may move on the way
+const DEPTH: usize = 4;
+const SPREAD: usize = 5;
+// a piece in its place outweighs any number of stickers
+const PLACED: isize = 1000;
+
+#[derive(Debug)]
+pub struct SolveError {
+    pub kind: &'static str,
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "found no algorithm for the remaining {} pieces",
+            self.kind
+        )
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+pub struct Step {
+    // what the step works on, e.g. `edges 12/55`
+    pub label: String,
+    pub twists: Vec<Twist>,
+}
+
+// one stage's algorithms, cut down to their effect on the pieces being placed, and for
+// every pair of positions the (shape, symmetry) moving a piece from the first to the second
+struct Stage {
+    shapes: Vec<Shape>,
+    arrows: HashMap<(usize, usize), Vec<(usize, usize)>>,
+    leaving: HashMap<usize, Vec<(usize, usize)>>,
+}
+
+// a shape's moves seen the same from everywhere, with where each turns the grips packed
+type Key = Vec<(usize, usize, u64)>;
+
+#[derive(Clone)]
+struct Shape {
+    twists: Vec<Twist>,
+    moves: Vec<(usize, usize, [u8; MAX_DEGREE])>,
+}
+
+// a shape moved by a symmetry, then conjugated by a setup twist if there is one
+#[derive(Clone, Copy)]
+struct Instance {
+    setup: Option<usize>,
+    shape: usize,
+    s: usize,
+}
+
+struct Alg {
+    twists: Vec<Twist>,
+    // position by position, the piece at `from` ends at `to` turned by `rot`; only the
+    // positions the twists change are listed
+    moves: Vec<(usize, usize, [u8; MAX_DEGREE])>,
+}
+
+pub struct Solver<'a> {
+    def: &'a PuzzleDef,
+    // the solved pieces; a piece's index is also the index of its home position
+    homes: Vec<Piece>,
+    position: HashMap<u64, usize>,
+    symmetries: Vec<Permutation>,
+    inverses: Vec<Permutation>,
+    // where each symmetry takes each position
+    placements: Vec<Vec<usize>>,
+    // the symmetries taking one position to another
+    reaching: HashMap<(usize, usize), Vec<usize>>,
+    pool: Vec<Alg>,
+    // every twist, as setups for the algorithms, with their inverses' indices
+    setups: Vec<Twist>,
+    setup_tables: Vec<[u8; MAX_DEGREE]>,
+    setup_places: Vec<Vec<usize>>,
+    inverse_setup: Vec<usize>,
+}
+
+impl<'a> Solver<'a> {
+    pub fn new(def: &'a PuzzleDef) -> Self {
+        let homes = PuzzleState::generate(def.base_pieces.clone(), &def.generators);
+        let position: HashMap<u64, usize> =
+            homes.iter().enumerate().map(|(i, p)| (p.sig, i)).collect();
+        let symmetries = closure(&def.generators);
+        let placements: Vec<Vec<usize>> = symmetries
+            .iter()
+            .map(|s| homes.iter().map(|p| position[&p.rotate(s).sig]).collect())
+            .collect();
+        let mut reaching: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (s, placement) in placements.iter().enumerate() {
+            for (k, &to) in placement.iter().enumerate() {
+                reaching.entry((k, to)).or_default().push(s);
+            }
+        }
+        let mut solver = Self {
+            def,
+            homes,
+            position,
+            inverses: symmetries.iter().map(Permutation::inverse).collect(),
+            symmetries,
+            placements,
+            reaching,
+            pool: Vec::new(),
+            setups: Vec::new(),
+            setup_tables: Vec::new(),
+            setup_places: Vec::new(),
+            inverse_setup: Vec::new(),
+        };
+        solver.pool = solver.pool();
+        solver.setups = (0..def.degree).flat_map(|c| solver.twists(c)).collect();
+        solver.setup_tables = solver.setups.iter().map(|t| piece::table(&t.rot)).collect();
+        solver.setup_places = (solver.setups.iter().zip(&solver.setup_tables))
+            .map(|(t, table)| {
+                solver
+                    .homes
+                    .iter()
+                    .map(|home| {
+                        let mut p = *home;
+                        if p.in_grip(t.grip) {
+                            p.rotate_by(table);
+                        }
+                        solver.position[&p.sig]
+                    })
+                    .collect()
+            })
+            .collect();
+        solver.inverse_setup = solver
+            .setups
+            .iter()
+            .map(|t| {
+                let inverse = t.inverse();
+                solver
+                    .setups
+                    .iter()
+                    .position(|u| u.grip == t.grip && u.rot == inverse.rot)
+                    .unwrap()
+            })
+            .collect();
+        solver
+    }
+
+    fn twists(&self, cell: usize) -> Vec<Twist> {
+        notation::cell_twists(self.def, cell)
+    }
+
+    fn alg(&self, twists: Vec<Twist>) -> Alg {
+        let tables: Vec<_> = twists.iter().map(|t| piece::table(&t.rot)).collect();
+        // pieces in none of the grips never move
+        let grips = twists.iter().fold(0u64, |m, t| m | 1 << t.grip);
+        let mut moves = Vec::new();
+        for (i, home) in self.homes.iter().enumerate() {
+            if home.sig & grips == 0 {
+                continue;
+            }
+            let mut p = Piece::from_mask(home.sig, home.degree());
+            for (t, table) in twists.iter().zip(&tables) {
+                if p.in_grip(t.grip) {
+                    p.rotate_by(table);
+                }
+            }
+            if p.sig != home.sig || !fixes(&p.att, p.sig) {
+                moves.push((i, self.position[&p.sig], p.att));
+            }
+        }
+        Alg { twists, moves }
+    }
+
+    fn pool(&self) -> Vec<Alg> {
+        let near = self.twists(0);
+        let across = self.twists(self.def.face_grips[0]);
+        let mut pool: Vec<Alg> = near.iter().map(|t| self.alg(vec![t.clone()])).collect();
+
+        let mut commutators: Vec<Alg> = Vec::new();
+        for x in &near {
+            for y in &across {
+                let alg = self.alg(commutator(slice::from_ref(x), slice::from_ref(y)));
+                if !alg.moves.is_empty() {
+                    commutators.push(alg);
+                }
+            }
+        }
+        commutators.sort_by_key(|a| a.moves.len());
+
+        let mut nested = Vec::new();
+        for inner in commutators.iter().take(NESTED) {
+            // only twists of the cells the inner commutator reaches can disturb it
+            let cells = inner
+                .moves
+                .iter()
+                .fold(0u64, |m, &(from, _, _)| m | self.homes[from].sig);
+            for cell in bits(cells) {
+                for z in self.twists(cell) {
+                    let alg = self.alg(commutator(&inner.twists, &[z]));
+                    if !alg.moves.is_empty() {
+                        nested.push(alg);
+                    }
+                }
+            }
+        }
+        pool.extend(commutators);
+        pool.extend(nested);
+        pool
+    }
+
+    // the steps solving `pieces`, which are in the order `PuzzleState::generate` gives them
+    pub fn solve(&self, pieces: &[Piece]) -> Result<Vec<Step>, SolveError> {
+        let mut pieces = pieces.to_vec();
+        let mut at = vec![0; pieces.len()];
+        let mut sizes: Vec<u32> = self.homes.iter().map(|p| p.sig.count_ones()).collect();
+        sizes.sort();
+        sizes.dedup();
+
+        let mut steps = Vec::new();
+        for (n, &size) in sizes.iter().enumerate() {
+            let of_type: Vec<usize> = (0..self.homes.len())
+                .filter(|&k| self.size(k) == size)
+                .collect();
+            let kind = self
+                .def
+                .sticker_shape(&self.homes[of_type[0]])
+                .map_or("centre", |s| s.kind());
+            // only worked out once the type turns out to need it
+            let mut stage = None;
+
+            loop {
+                // which piece is at each position
+                for (i, p) in pieces.iter().enumerate() {
+                    at[self.position[&p.sig]] = i;
+                }
+                let unplaced: Vec<usize> = of_type
+                    .iter()
+                    .copied()
+                    .filter(|&k| !(at[k] == k && fixes(&pieces[k].att, pieces[k].sig)))
+                    .collect();
+                if unplaced.is_empty() {
+                    break;
+                }
+                let stage = &*stage.get_or_insert_with(|| self.stage(size, &sizes[..n]));
+                // one instance gaining something, or a last piece turned home by several, or
+                // two that gain something together
+                let instances = unplaced
+                    .iter()
+                    .find_map(|&target| self.best(stage, target, &pieces, &at, 0))
+                    .map(|(instance, _)| vec![instance])
+                    .or_else(|| match unplaced[..] {
+                        [k] if at[k] == k => self.turn_home(stage, k, &pieces),
+                        _ => None,
+                    })
+                    .or_else(|| {
+                        self.lookahead(stage, &unplaced, &pieces, &at)
+                            .map(|(first, then)| vec![first, then])
+                    })
+                    .ok_or(SolveError { kind })?;
+                for instance in instances {
+                    let shape = &stage.shapes[instance.shape];
+                    let twists = self.place_twists(shape, instance.s, instance.setup);
+                    // the stage only follows its own type, so the larger pieces are twisted too
+                    for t in &twists {
+                        let table = piece::table(&t.rot);
+                        for p in pieces.iter_mut().filter(|p| p.in_grip(t.grip)) {
+                            p.rotate_by(&table);
+                        }
+                    }
+                    steps.push(Step {
+                        label: format!(
+                            "{kind}s {}/{}",
+                            of_type.len() - unplaced.len(),
+                            of_type.len()
+                        ),
+                        twists,
+                    });
+                }
+            }
+        }
+        Ok(steps)
+    }
+
+    fn size(&self, k: usize) -> u32 {
+        self.homes[k].sig.count_ones()
+    }
+
+    // the pool cut down to what moves pieces with `size` grips and nothing of the sizes
+    // already placed, keeping the shortest algorithm for each shape up to symmetry
+    fn stage(&self, size: u32, placed: &[u32]) -> Stage {
+        let mut shapes: HashMap<Key, Shape> = HashMap::new();
+        for alg in &self.pool {
+            if alg
+                .moves
+                .iter()
+                .any(|&(from, _, _)| placed.contains(&self.size(from)))
+            {
+                continue;
+            }
+            let moves: Vec<_> = alg
+                .moves
+                .iter()
+                .copied()
+                .filter(|&(from, _, _)| self.size(from) == size)
+                .collect();
+            if moves.is_empty() {
+                continue;
+            }
+            let shape = Shape {
+                twists: alg.twists.clone(),
+                moves,
+            };
+            keep_shortest(&mut shapes, self.canonical(&shape.moves), shape);
+        }
+
+        // a cycle followed by another taking every piece back where it was turns them in
+        // place, when the two turn the pieces differently; two such turns of the same pieces
+        // can then leave fewer of them turned
+        let cycles: Vec<&Shape> = shapes
+            .values()
+            .filter(|shape| {
+                shape.moves.len() <= CYCLE && shape.moves.iter().all(|&(from, to, _)| from != to)
+            })
+            .collect();
+        let mut turns = self.undo_pairs(&cycles, TURNED);
+        if turns.is_empty() {
+            // then the cycles agree on how they turn the pieces two at a time, and it takes
+            // longer chains of them
+            turns = self.products(&cycles);
+        }
+        for shape in turns {
+            keep_shortest(&mut shapes, self.canonical(&shape.moves), shape);
+        }
+        let turns: Vec<&Shape> = shapes
+            .values()
+            .filter(|shape| {
+                shape.moves.len() == TURNED && shape.moves.iter().all(|&(from, to, _)| from == to)
+            })
+            .collect();
+        let mut singles = self.undo_pairs(&turns, TURNED - 1);
+        if singles.is_empty() && !turns.is_empty() {
+            // a piece may still turn on its own when it takes more than two turns
+            singles = self.products(&turns);
+            singles.retain(|shape| shape.moves.len() < TURNED);
+        }
+        for shape in singles {
+            keep_shortest(&mut shapes, self.canonical(&shape.moves), shape);
+        }
+
+        let mut shapes: Vec<(Key, Shape)> = shapes.into_iter().collect();
+        shapes.sort_by(|(a, x), (b, y)| (x.twists.len(), a).cmp(&(y.twists.len(), b)));
+        let shapes: Vec<Shape> = shapes.into_iter().map(|(_, shape)| shape).collect();
+
+        let mut arrows: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+        let mut leaving: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+        for (i, shape) in shapes.iter().enumerate() {
+            for (s, placement) in self.placements.iter().enumerate() {
+                for &(from, to, _) in &shape.moves {
+                    let (from, to) = (placement[from], placement[to]);
+                    arrows.entry((from, to)).or_default().push((i, s));
+                    leaving.entry(from).or_default().push((i, s));
+                }
+            }
+        }
+        Stage {
+            shapes,
+            arrows,
+            leaving,
+        }
+    }
+
+    // each shape followed by another moved so that it takes the pieces back, when that
+    // leaves at most `most` of them turned
+    fn undo_pairs(&self, shapes: &[&Shape], most: usize) -> Vec<Shape> {
+        // no symmetry may turn a cycle around, but undoing one can
+        let backs: Vec<Shape> = shapes
+            .iter()
+            .flat_map(|&shape| [shape.clone(), self.undo(shape)])
+            .collect();
+        let mut seen = HashSet::new();
+        let mut pairs = Vec::new();
+        for there in shapes {
+            let (a, b, _) = there.moves[0];
+            for back in backs
+                .iter()
+                .filter(|back| back.moves.len() == there.moves.len())
+            {
+                let (c, d, _) = back.moves[0];
+                'pair: for &s in self.reaching.get(&(c, b)).into_iter().flatten() {
+                    if self.placements[s][d] != a {
+                        continue;
+                    }
+                    let undo = self.place(back, s, None);
+                    let mut moves = Vec::new();
+                    for &(from, to, rot) in &there.moves {
+                        let Some(&(_, _, undo)) =
+                            undo.iter().find(|&&(f, t, _)| f == to && t == from)
+                        else {
+                            continue 'pair;
+                        };
+                        let mut table = [0; MAX_DEGREE];
+                        for k in bits(self.homes[from].sig) {
+                            table[k] = undo[rot[k] as usize];
+                        }
+                        if !fixes(&table, self.homes[from].sig) {
+                            moves.push((from, from, table));
+                        }
+                    }
+                    if moves.is_empty() || moves.len() > most || !seen.insert(moves.clone()) {
+                        continue;
+                    }
+                    let mut twists = there.twists.clone();
+                    twists.extend(self.relocate_twists(&back.twists, s));
+                    pairs.push(Shape { twists, moves });
+                }
+            }
+        }
+        pairs
+    }
+
+    // products of the cycles, each picking up a piece where the last left one, moving few
+    // pieces on the way; the ones leaving pieces turned in place
+    fn products(&self, cycles: &[&Shape]) -> Vec<Shape> {
+        let gens: Vec<Shape> = cycles
+            .iter()
+            .flat_map(|&shape| [shape.clone(), self.undo(shape)])
+            .collect();
+        let mut known: HashSet<Key> = HashSet::new();
+        let mut frontier: Vec<Shape> = cycles.iter().map(|&shape| shape.clone()).collect();
+        let mut turns = Vec::new();
+        for depth in 1..DEPTH {
+            let last = depth == DEPTH - 1;
+            let mut next = Vec::new();
+            for there in &frontier {
+                let away: Vec<(usize, usize)> = there
+                    .moves
+                    .iter()
+                    .filter(|&&(from, to, _)| from != to)
+                    .map(|&(from, to, _)| (from, to))
+                    .collect();
+                for back in &gens {
+                    // closing a turn takes every piece still away back where it was
+                    if last && away.len() > back.moves.len() {
+                        continue;
+                    }
+                    for &(_, to, _) in &there.moves {
+                        for &(c, _, _) in &back.moves {
+                            for &s in self.reaching.get(&(c, to)).into_iter().flatten() {
+                                let placement = &self.placements[s];
+                                if last
+                                    && !away.iter().all(|&(from, to)| {
+                                        back.moves.iter().any(|&(c, d, _)| {
+                                            placement[c] == to && placement[d] == from
+                                        })
+                                    })
+                                {
+                                    continue;
+                                }
+                                let moves = self.compose(&there.moves, &self.place(back, s, None));
+                                let turn = moves.iter().all(|&(from, to, _)| from == to);
+                                // the last link is only worth it if it closes a turn
+                                if moves.is_empty() || moves.len() > SPREAD || last && !turn {
+                                    continue;
+                                }
+                                if !known.insert(self.canonical(&moves)) {
+                                    continue;
+                                }
+                                let mut twists = there.twists.clone();
+                                twists.extend(self.relocate_twists(&back.twists, s));
+                                let shape = Shape { twists, moves };
+                                if turn {
+                                    turns.push(shape.clone());
+                                }
+                                next.push(shape);
+                            }
+                        }
+                    }
+                }
+            }
+            frontier = next;
+        }
+        turns
+    }
+
+    // the moves of `first` then `then`, leaving out the pieces that end where they were
+    fn compose(
+        &self,
+        first: &[(usize, usize, [u8; MAX_DEGREE])],
+        then: &[(usize, usize, [u8; MAX_DEGREE])],
+    ) -> Vec<(usize, usize, [u8; MAX_DEGREE])> {
+        let step = |moves: &[(usize, usize, [u8; MAX_DEGREE])], k: usize| {
+            moves.iter().find(|m| m.0 == k).copied()
+        };
+        let starts = first.iter().map(|m| m.0).chain(
+            then.iter()
+                .map(|m| m.0)
+                .filter(|&k| !first.iter().any(|m| m.1 == k)),
+        );
+        let mut moves = Vec::new();
+        for from in starts {
+            let mut table = [0; MAX_DEGREE];
+            let mut to = from;
+            for k in bits(self.homes[from].sig) {
+                table[k] = k as u8;
+            }
+            for moves in [first, then] {
+                if let Some((_, next, rot)) = step(moves, to) {
+                    for k in bits(self.homes[from].sig) {
+                        table[k] = rot[table[k] as usize];
+                    }
+                    to = next;
+                }
+            }
+            if to != from || !fixes(&table, self.homes[from].sig) {
+                moves.push((from, to, table));
+            }
+        }
+        moves.sort_by_key(|m| m.0);
+        moves
+    }
+
+    fn undo(&self, shape: &Shape) -> Shape {
+        let moves = shape
+            .moves
+            .iter()
+            .map(|&(from, to, rot)| {
+                let mut table = [0; MAX_DEGREE];
+                for k in bits(self.homes[from].sig) {
+                    table[rot[k] as usize] = k as u8;
+                }
+                (to, from, table)
+            })
+            .collect();
+        let twists = shape.twists.iter().rev().map(Twist::inverse).collect();
+        Shape { twists, moves }
+    }
+
+    // the smallest image of a shape's moves under the symmetries, for spotting repeats
+    fn canonical(&self, moves: &[(usize, usize, [u8; MAX_DEGREE])]) -> Key {
+        // the smallest image starts with the first position any of the pieces can reach
+        let first = |k: usize| {
+            (0..=k)
+                .find(|&j| self.reaching.contains_key(&(k, j)))
+                .unwrap()
+        };
+        let least = moves
+            .iter()
+            .map(|&(from, _, _)| first(from))
+            .min()
+            .unwrap_or(0);
+        let symmetries = moves
+            .iter()
+            .flat_map(|&(from, _, _)| self.reaching.get(&(from, least)).into_iter().flatten());
+        let mut best: Option<Key> = None;
+        for &s in symmetries {
+            let placement = &self.placements[s];
+            let mut key: Key = moves
+                .iter()
+                .map(|&(from, to, rot)| {
+                    let rot = self.relocate(&rot, s, self.homes[placement[from]].sig);
+                    // where the grips of the piece go, packed six bits each
+                    let turn = bits(self.homes[placement[from]].sig)
+                        .fold(0, |k, c| k << 6 | rot[c] as u64);
+                    (placement[from], placement[to], turn)
+                })
+                .collect();
+            key.sort();
+            if best.as_ref().is_none_or(|b| key < *b) {
+                best = Some(key);
+            }
+        }
+        best.unwrap_or_default()
+    }
+
+    // a rotation seen through symmetry `s`
+    // (only on the grips in `sig`, as a move's rotation is only ever applied to the piece at
+    // the position it starts from)
+    fn relocate(&self, rot: &[u8; MAX_DEGREE], s: usize, sig: u64) -> [u8; MAX_DEGREE] {
+        let (sym, inv) = (&self.symmetries[s], &self.inverses[s]);
+        let mut table = [0; MAX_DEGREE];
+        for k in bits(sig) {
+            table[k] = sym.permute(rot[inv.permute(k)] as usize) as u8;
+        }
+        table
+    }
+
+    // twists seen through symmetry `s`
+    fn relocate_twists(&self, twists: &[Twist], s: usize) -> Vec<Twist> {
+        let (sym, inv) = (&self.symmetries[s], &self.inverses[s]);
+        twists
+            .iter()
+            .map(|t| Twist {
+                grip: sym.permute(t.grip),
+                rot: t.rot.conjugate(inv),
+            })
+            .collect()
+    }
+
+    // the fewest instances turning only the piece at home at `k`, that together turn it right
+    fn turn_home(&self, stage: &Stage, k: usize, pieces: &[Piece]) -> Option<Vec<Instance>> {
+        let sig = self.homes[k].sig;
+        let key = |att: &[u8; MAX_DEGREE]| bits(sig).fold(0, |key, c| key << 6 | att[c] as u64);
+        let turns: Vec<(Instance, [u8; MAX_DEGREE])> = stage
+            .arrows
+            .get(&(k, k))
+            .into_iter()
+            .flatten()
+            .filter(|&&(shape, _)| stage.shapes[shape].moves.len() == 1)
+            .map(|&(shape, s)| {
+                let rot = self.place(&stage.shapes[shape], s, None)[0].2;
+                (
+                    Instance {
+                        setup: None,
+                        shape,
+                        s,
+                    },
+                    rot,
+                )
+            })
+            .collect();
+        // breadth first over the attitudes, each reached from the one before it
+        let mut came: HashMap<u64, (u64, usize)> = HashMap::new();
+        let mut atts = HashMap::from([(key(&pieces[k].att), pieces[k].att)]);
+        let mut frontier = vec![pieces[k].att];
+        while !frontier.is_empty() {
+            let mut next = Vec::new();
+            for att in frontier {
+                if fixes(&att, sig) {
+                    let mut path = Vec::new();
+                    let mut at = key(&att);
+                    while let Some(&(before, turn)) = came.get(&at) {
+                        path.push(turns[turn].0);
+                        at = before;
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                for (turn, (_, rot)) in turns.iter().enumerate() {
+                    let mut turned = att;
+                    for c in bits(sig) {
+                        turned[c] = rot[att[c] as usize];
+                    }
+                    if atts.insert(key(&turned), turned).is_none() {
+                        came.insert(key(&turned), (key(&att), turn));
+                        next.push(turned);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        None
+    }
+
+    // two instances moving unplaced pieces, the second picking up something the first moved,
+    // for when no single one gains anything
+    fn lookahead(
+        &self,
+        stage: &Stage,
+        unplaced: &[usize],
+        pieces: &[Piece],
+        at: &[usize],
+    ) -> Option<(Instance, Instance)> {
+        // each piece anywhere as the algorithms are, and behind a setup turned where it is
+        // or moved without losing a piece, once for each distinct way of moving the pieces
+        let mut seen = HashSet::new();
+        let mut moving = Vec::new();
+        for &target in unplaced {
+            let from = self.position[&pieces[target].sig];
+            for setup in self.setups_on(self.homes[from].sig) {
+                let a = setup.map_or(from, |t| self.setup_places[t][from]);
+                for &(shape, s) in stage.leaving.get(&a).into_iter().flatten() {
+                    let mut moves = self.place(&stage.shapes[shape], s, setup);
+                    let gain = self.gain(&moves, pieces, at);
+                    let turn = moves.iter().any(|&(f, t, _)| f == from && t == from);
+                    if setup.is_some() && !turn && gain <= -PLACED / 2 {
+                        continue;
+                    }
+                    moves.sort_by_key(|m| m.0);
+                    if seen.insert(moves.clone()) {
+                        moving.push((Instance { setup, shape, s }, moves, gain));
+                    }
+                }
+            }
+        }
+        let mut touching: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (j, (_, moves, _)) in moving.iter().enumerate() {
+            for &(from, _, _) in moves {
+                touching.entry(from).or_default().push(j);
+            }
+        }
+
+        // the second as it was found among those, or failing that, the best for a piece
+        // the first moved
+        for deep in [false, true] {
+            let mut best = None;
+            let mut best_gain = 0;
+            let (mut after, mut now_at) = (pieces.to_vec(), at.to_vec());
+            for &(first, ref moves, gain) in &moving {
+                // the best for each piece is slow, so only after firsts that lose nothing
+                if deep && first.setup.is_some() && gain <= -PLACED / 2 {
+                    continue;
+                }
+                for &(from, to, rot) in moves {
+                    after[at[from]].rotate_by(&rot);
+                    now_at[to] = at[from];
+                }
+                for &(from, to, _) in moves {
+                    let found = if deep {
+                        self.best(stage, at[from], &after, &now_at, best_gain - gain)
+                    } else {
+                        touching
+                            .get(&to)
+                            .into_iter()
+                            .flatten()
+                            .map(|&j| (moving[j].0, self.gain(&moving[j].1, &after, &now_at)))
+                            .max_by_key(|&(_, more)| more)
+                    };
+                    if let Some((then, more)) = found
+                        && gain + more > best_gain
+                    {
+                        best = Some((first, then));
+                        best_gain = gain + more;
+                    }
+                }
+                for &(from, to, _) in moves {
+                    after[at[from]] = pieces[at[from]];
+                    now_at[to] = at[to];
+                }
+            }
+            if best.is_some() {
+                return best;
+            }
+        }
+        None
+    }
+
+    // the best instance bringing home the piece that belongs at `target`, with its moves
+    fn best(
+        &self,
+        stage: &Stage,
+        target: usize,
+        pieces: &[Piece],
+        at: &[usize],
+        floor: isize,
+    ) -> Option<(Instance, isize)> {
+        let from = self.position[&pieces[target].sig];
+        // setups twist a cell either position is in
+        let cells = self.homes[from].sig | self.homes[target].sig;
+
+        let mut best = None;
+        let mut best_gain = floor;
+        for setup in self.setups_on(cells) {
+            // setups only when the algorithms as they are do nothing for the piece
+            if setup.is_some() && best.is_some() {
+                break;
+            }
+            let (a, b) = match setup {
+                Some(t) => (self.setup_places[t][from], self.setup_places[t][target]),
+                None => (from, target),
+            };
+            for &(shape, s) in stage.arrows.get(&(a, b)).into_iter().flatten() {
+                let instance = Instance { setup, shape, s };
+                let moves = self.place(&stage.shapes[shape], s, setup);
+                let gain = self.gain(&moves, pieces, at);
+                if gain > best_gain {
+                    best = Some((instance, gain));
+                    best_gain = gain;
+                }
+            }
+        }
+        best
+    }
+
+    // no setup, or a twist of one of `cells`
+    fn setups_on(&self, cells: u64) -> impl Iterator<Item = Option<usize>> + '_ {
+        std::iter::once(None).chain(
+            self.setups
+                .iter()
+                .enumerate()
+                .filter(move |(_, t)| cells >> t.grip & 1 == 1)
+                .map(|(i, _)| Some(i)),
+        )
+    }
+
+    // a shape's moves seen through symmetry `s`, then conjugated by `setup`
+    fn place(
+        &self,
+        shape: &Shape,
+        s: usize,
+        setup: Option<usize>,
+    ) -> Vec<(usize, usize, [u8; MAX_DEGREE])> {
+        let placement = &self.placements[s];
+        shape
+            .moves
+            .iter()
+            .map(|&(from, to, rot)| {
+                let (from, to) = (placement[from], placement[to]);
+                let rot = self.relocate(&rot, s, self.homes[from].sig);
+                let Some(t) = setup else {
+                    return (from, to, rot);
+                };
+                // the setup twist brings the piece to `from` and the undoing twist takes it
+                // on from `to`, turning it only if the position is in the twist's grip
+                let (grip, inverse) = (self.setups[t].grip, self.inverse_setup[t]);
+                let turned = |k: usize| self.homes[k].in_grip(grip);
+                let places = &self.setup_places[inverse];
+                let mut table = [0; MAX_DEGREE];
+                for k in bits(self.homes[places[from]].sig) {
+                    let r = if turned(from) {
+                        self.setup_tables[t][k]
+                    } else {
+                        k as u8
+                    };
+                    let r = rot[r as usize];
+                    table[k] = if turned(to) {
+                        self.setup_tables[inverse][r as usize]
+                    } else {
+                        r
+                    };
+                }
+                (places[from], places[to], table)
+            })
+            .collect()
+    }
+
+    fn place_twists(&self, shape: &Shape, s: usize, setup: Option<usize>) -> Vec<Twist> {
+        let relocated = self.relocate_twists(&shape.twists, s);
+        match setup {
+            Some(t) => std::iter::once(self.setups[t].clone())
+                .chain(relocated)
+                .chain(std::iter::once(self.setups[t].inverse()))
+                .collect(),
+            None => relocated,
+        }
+    }
+
+    fn gain(
+        &self,
+        moves: &[(usize, usize, [u8; MAX_DEGREE])],
+        pieces: &[Piece],
+        at: &[usize],
+    ) -> isize {
+        let mut gain = 0;
+        for &(from, to, rot) in moves {
+            let i = at[from];
+            let att = &pieces[i].att;
+            gain -= self.score(i, from, |c| att[c] as usize == c);
+            gain += self.score(i, to, |c| rot[att[c] as usize] as usize == c);
+        }
+        gain
+    }
+
+    // the stickers of piece `i` at position `k` on their own cell, and more
+    // for the piece being home
+    fn score(&self, i: usize, k: usize, right: impl Fn(usize) -> bool) -> isize {
+        let sig = self.homes[i].sig;
+        let right = bits(sig).filter(|&c| right(c)).count() as isize;
+        if i == k && right == sig.count_ones() as isize {
+            PLACED + right
+        } else {
+            right
+        }
+    }
+}
+
+fn keep_shortest(shapes: &mut HashMap<Key, Shape>, key: Key, shape: Shape) {
+    match shapes.get(&key) {
+        Some(known) if known.twists.len() <= shape.twists.len() => (),
+        _ => {
+            shapes.insert(key, shape);
+        }
+    }
+}
+
+// the whole group generated by `gens`
+fn closure(gens: &[Permutation]) -> Vec<Permutation> {
+    let degree = gens.first().map_or(0, |g| g.deg);
+    let mut seen = HashSet::from([Permutation::identity(degree)]);
+    let mut elements = vec![Permutation::identity(degree)];
+    let mut k = 0;
+    while k < elements.len() {
+        for g in gens {
+            let next = elements[k].product(g);
+            if seen.insert(next.clone()) {
+                elements.push(next);
+            }
+        }
+        k += 1;
+    }
+    elements
+}
+
+fn commutator(x: &[Twist], y: &[Twist]) -> Vec<Twist> {
+    let inverse = |w: &[Twist]| w.iter().rev().map(Twist::inverse).collect::<Vec<_>>();
+    [x.to_vec(), y.to_vec(), inverse(x), inverse(y)].concat()
+}
+
+// whether an attitude leaves each of the grips in `sig` where it was
+fn fixes(att: &[u8; MAX_DEGREE], sig: u64) -> bool {
+    bits(sig).all(|c| att[c] as usize == c)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::puzzle::state::SCRAMBLE_LENGTH;
+
+    #[test]
+    fn solves_scrambled_builtins() {
+        for name in PuzzleDef::builtin_names() {
+            let def = Arc::new(PuzzleDef::named(name).unwrap());
+            let mut state = PuzzleState::new(def.clone());
+            state.scramble(1, SCRAMBLE_LENGTH);
+            let steps = Solver::new(&def).solve(&state.pieces).unwrap();
+            for twist in steps.iter().flat_map(|s| &s.twists) {
+                state.twist(twist);
+            }
+            assert!(state.is_solved(), "{name} is left unsolved");
+        }
+    }
+}
@@ -1,97 +1,244 @@
-use rand::RngExt;
+use std::{collections::HashSet, sync::Arc};
 
-use crate::puzzle::{perm::Permutation, piece::Piece, setup11c, twist::Twist};
+use rand::{RngExt, SeedableRng, rngs::StdRng};
+
+use crate::puzzle::{
+    def::PuzzleDef,
+    history::History,
+    notation::{Move, TwistKind},
+    perm::Permutation,
+    piece::{self, Piece},
+    twist::Twist,
+};
+
+pub const SCRAMBLE_LENGTH: u32 = 1000;
 
 pub struct PuzzleState {
+    pub def: Arc<PuzzleDef>,
     pub degree: usize,
     pub pieces: Vec<Piece>,
     pub twist_stack: Vec<Twist>,
-    pub is_solved: bool,
+    pub history: History,
+    pub scramble: Vec<Twist>,
+    pub scramble_seed: Option<u64>,
+    // since the last reset; undone twists leave `twist_stack`, so they are counted here
+    pub undos: usize,
+    // turns of the whole puzzle since the last reset; they change only how it is seen, so
+    // the view counts them here
+    pub rotations: usize,
+    // kept up to date by `twist`, which only looks at the pieces it moves
+    unsolved: usize,
+    // the pieces in each grip, as a bitset of piece indices `words` long per grip
+    in_grip: Vec<u64>,
+    words: usize,
+    // each piece's signature when solved; `att` takes it to the current one
+    home: Vec<u64>,
 }
 
 impl PuzzleState {
-    pub fn generate(mut base_pieces: Vec<Piece>, generators: &Vec<Permutation>) -> Self {
-        let mut gen_pieces = Vec::new();
-        while !base_pieces.is_empty() {
-            'generator_loop: for g in generators {
-                let mut new = base_pieces[0].clone().rotate(g);
-                for piece in &gen_pieces {
-                    if new.overlaps(piece) {
-                        continue 'generator_loop;
-                    }
-                }
-                new.att = Permutation::identity(new.degree());
-                base_pieces.push(new);
+    pub fn new(def: Arc<PuzzleDef>) -> Self {
+        let pieces = Self::generate(def.base_pieces.clone(), &def.generators);
+        let mut state = Self {
+            degree: def.degree,
+            home: pieces.iter().map(|p| p.sig).collect(),
+            def,
+            pieces,
+            twist_stack: Vec::new(),
+            history: History::new(),
+            scramble: Vec::new(),
+            scramble_seed: None,
+            undos: 0,
+            rotations: 0,
+            unsolved: 0,
+            in_grip: Vec::new(),
+            words: 0,
+        };
+        state.sort_into_grips();
+        state
+    }
+
+    fn sort_into_grips(&mut self) {
+        self.words = self.pieces.len().div_ceil(64);
+        self.in_grip = vec![0; self.degree * self.words];
+        for (i, piece) in self.pieces.iter().enumerate() {
+            for g in piece::bits(piece.sig) {
+                self.in_grip[g * self.words + i / 64] |= 1 << (i % 64);
             }
-            gen_pieces.push(base_pieces.swap_remove(0));
         }
-        Self {
-            degree: generators[0].deg,
-            pieces: gen_pieces,
-            twist_stack: Vec::new(),
-            is_solved: true,
+    }
+
+    pub fn generate(base_pieces: Vec<Piece>, generators: &[Permutation]) -> Vec<Piece> {
+        let mut seen: HashSet<u64> = base_pieces.iter().map(|p| p.sig).collect();
+        let mut gen_pieces = base_pieces;
+        let mut i = 0;
+        while i < gen_pieces.len() {
+            for g in generators {
+                let sig = gen_pieces[i].rotate(g).sig;
+                if seen.insert(sig) {
+                    gen_pieces.push(Piece::from_mask(sig, gen_pieces[i].degree()));
+                }
+            }
+            i += 1;
         }
+        gen_pieces
     }
 
     pub fn twist(&mut self, twist: &Twist) {
-        let mut new_pieces = Vec::new();
-        for piece in &self.pieces {
-            if piece.grip_state(twist.grip) == 1 {
-                new_pieces.push(piece.rotate(&twist.rot));
-            } else {
-                new_pieces.push(piece.clone());
+        let rot = piece::table(&twist.rot);
+        // the grip's own pieces stay in it, so its bits don't change while we go through them
+        debug_assert_eq!(rot[twist.grip] as usize, twist.grip);
+        for w in 0..self.words {
+            for bit in piece::bits(self.in_grip[twist.grip * self.words + w]) {
+                let i = w * 64 + bit;
+                let old = self.pieces[i].sig;
+                self.pieces[i].rotate_by(&rot);
+                let new = self.pieces[i].sig;
+                for g in piece::bits(old ^ new) {
+                    self.in_grip[g * self.words + w] ^= 1 << bit;
+                }
+                // a piece is solved once it is back in its home grips, as `Piece::is_solved`
+                match (old == self.home[i], new == self.home[i]) {
+                    (true, false) => self.unsolved += 1,
+                    (false, true) => self.unsolved -= 1,
+                    _ => (),
+                }
             }
         }
-        self.pieces = new_pieces;
-        self.is_solved = self.check_solved();
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.unsolved == 0
+    }
+
+    // whether the pieces belonging to a step of the solving filter, those whose grips it all
+    // shows, are solved
+    pub fn filter_solved(&self, filter: &[u8]) -> bool {
+        self.pieces.iter().all(|p| {
+            // `att` takes the piece's own grips to the cells it is in
+            p.is_solved() || (0..p.degree()).any(|g| p.in_grip(p.att[g] as usize) && filter[g] == 0)
+        })
     }
 
     pub fn twist_move(&mut self, twist: &Twist) {
         self.twist(twist);
         self.twist_stack.push(twist.clone());
+        self.history.push(twist);
     }
 
-    pub fn undo(&mut self) {
-        if let Some(t) = self.twist_stack.pop() {
-            self.twist(&t.inverse());
-        }
+    // undo and redo return the twist they applied
+    pub fn undo(&mut self) -> Option<Twist> {
+        let t = self.history.undo()?.inverse();
+        self.twist_stack.pop();
+        self.undos += 1;
+        self.twist(&t);
+        Some(t)
     }
 
-    pub fn scramble(&mut self, n: u32) {
-        let mut rng = rand::rng();
+    pub fn redo(&mut self) -> Option<Twist> {
+        let t = self.history.redo()?;
+        self.twist(&t);
+        self.twist_stack.push(t.clone());
+        Some(t)
+    }
 
-        for _ in 0..n {
-            let g = rng.random_range(0..self.degree);
-            let f = rng.random_range(1..self.degree);
+    // the same seed always gives the same scramble
+    pub fn scramble(&mut self, seed: u64, n: u32) {
+        for twist in Self::scramble_twists(&self.def, seed, n) {
+            self.twist(&twist);
+            self.scramble.push(twist);
+        }
+        self.scramble_seed = Some(seed);
+    }
 
-            let recenter = setup11c::cell_recenter(g);
-            let face_recenter = setup11c::face_recenter(f);
+    pub fn scramble_twists(def: &PuzzleDef, seed: u64, n: u32) -> Vec<Twist> {
+        let mut rng = StdRng::seed_from_u64(seed);
 
-            self.twist(&Twist {
-                grip: g,
-                rot: recenter
-                    .product(&face_recenter)
-                    .product(&setup11c::face_rot(rng.random_bool(0.5)))
-                    .product(&face_recenter.inverse())
-                    .product(&recenter.inverse()),
+        (0..n)
+            .map(|_| {
+                Move {
+                    cell: rng.random_range(0..def.degree),
+                    face: def.face_grips[rng.random_range(0..def.face_grips.len())],
+                    kind: TwistKind::Face {
+                        ccw: rng.random_bool(0.5),
+                    },
+                }
+                .twist(def)
             })
-        }
+            .collect()
     }
 
     pub fn reset(&mut self) {
-        let mut new_pieces = Vec::new();
-        for piece in &self.pieces {
-            new_pieces.push(piece.rotate(&piece.att.inverse()));
+        for piece in &mut self.pieces {
+            *piece = piece.rotate(&piece.attitude().inverse());
         }
-        self.pieces = new_pieces;
+        self.sort_into_grips();
+        self.unsolved = 0;
+        self.twist_stack.clear();
+        self.history.clear();
+        self.scramble.clear();
+        self.scramble_seed = None;
+        self.undos = 0;
+        self.rotations = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scrambled(def: &Arc<PuzzleDef>, seed: u64) -> PuzzleState {
+        let mut state = PuzzleState::new(def.clone());
+        state.scramble(seed, 100);
+        state
     }
 
-    pub fn check_solved(&mut self) -> bool {
-        for piece in &self.pieces {
-            if !piece.is_solved() {
-                return false;
+    fn same_twists(a: &[Twist], b: &[Twist]) -> bool {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(a, b)| a.grip == b.grip && a.rot == b.rot)
+    }
+
+    #[test]
+    fn scrambles_depend_only_on_the_seed() {
+        let def = Arc::new(PuzzleDef::builtin());
+        let (a, b, c) = (scrambled(&def, 1), scrambled(&def, 1), scrambled(&def, 2));
+        assert!(same_twists(&a.scramble, &b.scramble));
+        assert_eq!(a.pieces, b.pieces);
+        assert_eq!(a.scramble_seed, Some(1));
+        assert!(!same_twists(&a.scramble, &c.scramble));
+        assert_ne!(a.pieces, c.pieces);
+        assert!(!a.is_solved());
+    }
+
+    #[test]
+    fn generated_pieces_are_all_different() {
+        let def = Arc::new(PuzzleDef::builtin());
+        let state = PuzzleState::new(def);
+        let sigs: HashSet<u64> = state.pieces.iter().map(|p| p.sig).collect();
+        assert_eq!(sigs.len(), state.pieces.len());
+        // 11 cells, 55 faces, 55 edges and 11 vertices
+        assert_eq!(state.pieces.len(), 132);
+    }
+
+    #[test]
+    fn twists_keep_the_grip_lists_and_solved_count() {
+        for name in PuzzleDef::builtin_names() {
+            let def = Arc::new(PuzzleDef::named(name).unwrap());
+            let mut state = scrambled(&def, 3);
+            for (g, words) in state.in_grip.chunks(state.words).enumerate() {
+                for (i, piece) in state.pieces.iter().enumerate() {
+                    assert_eq!(words[i / 64] >> (i % 64) & 1 == 1, piece.in_grip(g));
+                }
+            }
+            let unsolved = state.pieces.iter().filter(|p| !p.is_solved()).count();
+            assert_eq!(state.unsolved, unsolved);
+
+            for twist in state.scramble.clone().iter().rev() {
+                state.twist(&twist.inverse());
             }
+            assert!(state.is_solved());
+            assert!(state.pieces.iter().all(|p| p.is_solved()));
         }
-        true
     }
 }
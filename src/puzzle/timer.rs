@@ -0,0 +1,374 @@
+// A speedsolving timer: a scramble arms it, the first twist or the end of the inspection
+// time starts it and the puzzle being solved stops it, with a split each time the step of the
+// solving filter on show is done. Finished solves go into a session, which is written out and
+// read back as CSV:
+//
+//     solve,time,ao5,ao12,twists,seed,assisted,step 1,step 2
+//     1,41.250,,,212,1234,,9.870,20.115
+//
+// Times are in seconds, splits counted from the start. A solve is assisted when twists not
+// made by hand went into it, e.g. from the solver, the algorithm finder or a macro; it is
+// kept but left out of the statistics. The averages are only written out; reading a session
+// back works them out again.
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crate::puzzle::state::PuzzleState;
+
+#[derive(Debug)]
+pub struct SessionError {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.msg)
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+#[derive(Clone, Copy)]
+enum Phase {
+    Idle,
+    // scrambled, waiting for the first twist
+    Ready,
+    Inspecting(Instant),
+    Running(Instant),
+    Done(Duration),
+}
+
+// what the timer shows
+pub enum Reading {
+    Idle,
+    Ready,
+    // the inspection time left
+    Inspecting(Duration),
+    Running(Duration),
+    Done(Duration),
+}
+
+pub struct Timer {
+    pub inspection: Option<Duration>,
+    phase: Phase,
+    // the twists made when last updated, to spot the first one
+    twists: usize,
+    // the filter steps done so far and when
+    splits: Vec<(usize, Duration)>,
+    // whether twists not made by hand went into this solve
+    assisted: bool,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self {
+            inspection: None,
+            phase: Phase::Idle,
+            twists: 0,
+            splits: Vec::new(),
+            assisted: false,
+        }
+    }
+
+    // called right after scrambling
+    pub fn arm(&mut self, now: Instant) {
+        self.phase = match self.inspection {
+            Some(_) => Phase::Inspecting(now),
+            None => Phase::Ready,
+        };
+        self.twists = 0;
+        self.splits.clear();
+        self.assisted = false;
+    }
+
+    pub fn cancel(&mut self) {
+        self.phase = Phase::Idle;
+        self.splits.clear();
+        self.assisted = false;
+    }
+
+    // called before making twists not by hand
+    pub fn assist(&mut self) {
+        self.assisted = true;
+    }
+
+    // follows the puzzle and returns the solve when it is finished
+    pub fn update(&mut self, now: Instant, state: &PuzzleState, filter: usize) -> Option<Solve> {
+        let twisted = state.twist_stack.len() != self.twists;
+        self.twists = state.twist_stack.len();
+        match self.phase {
+            Phase::Ready if twisted => self.phase = Phase::Running(now),
+            Phase::Inspecting(_) if twisted => self.phase = Phase::Running(now),
+            // inspecting too long starts the timer anyway
+            Phase::Inspecting(since) => {
+                if let Some(limit) = self.inspection
+                    && now >= since + limit
+                {
+                    self.phase = Phase::Running(since + limit);
+                }
+            }
+            // twisting on after a solve puts the time away
+            Phase::Done(_) if twisted => self.phase = Phase::Idle,
+            _ => {}
+        }
+
+        let Phase::Running(start) = self.phase else {
+            return None;
+        };
+        let time = now - start;
+        if state.is_solved() {
+            self.phase = Phase::Done(time);
+            return Some(Solve {
+                time,
+                twists: state.twist_stack.len(),
+                seed: state.scramble_seed,
+                splits: std::mem::take(&mut self.splits),
+                assisted: self.assisted,
+            });
+        }
+        // filter 0 shows the whole puzzle, which is the solve itself
+        if filter > 0
+            && !self.splits.iter().any(|&(f, _)| f == filter)
+            && state.filter_solved(&state.def.filters[filter])
+        {
+            self.splits.push((filter, time));
+        }
+        None
+    }
+
+    pub fn reading(&self, now: Instant) -> Reading {
+        match self.phase {
+            Phase::Idle => Reading::Idle,
+            Phase::Ready => Reading::Ready,
+            Phase::Inspecting(since) => {
+                let limit = self.inspection.unwrap_or_default();
+                Reading::Inspecting((since + limit).saturating_duration_since(now))
+            }
+            Phase::Running(start) => Reading::Running(now - start),
+            Phase::Done(time) => Reading::Done(time),
+        }
+    }
+
+    pub fn splits(&self) -> &[(usize, Duration)] {
+        &self.splits
+    }
+}
+
+#[derive(Clone)]
+pub struct Solve {
+    pub time: Duration,
+    pub twists: usize,
+    pub seed: Option<u64>,
+    pub splits: Vec<(usize, Duration)>,
+    pub assisted: bool,
+}
+
+#[derive(Default)]
+pub struct Session {
+    pub solves: Vec<Solve>,
+}
+
+impl Session {
+    // the solves the statistics are of
+    pub fn timed(&self) -> usize {
+        times(&self.solves).len()
+    }
+
+    pub fn best(&self) -> Option<Duration> {
+        times(&self.solves).into_iter().min()
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        let times = times(&self.solves);
+        let n = times.len() as u32;
+        (n > 0).then(|| times.into_iter().sum::<Duration>() / n)
+    }
+
+    // the average of the last `n` solves leaving out the best and the worst, e.g. ao5
+    pub fn average(&self, n: usize) -> Option<Duration> {
+        average(&times(&self.solves), n)
+    }
+
+    pub fn write_csv(&self) -> String {
+        let steps = (self.solves.iter())
+            .flat_map(|s| s.splits.iter().map(|&(step, _)| step))
+            .max()
+            .unwrap_or(0);
+        let mut out = String::from("solve,time,ao5,ao12,twists,seed,assisted");
+        for step in 1..=steps {
+            out += &format!(",step {step}");
+        }
+        out += "\n";
+
+        let secs = |d: Duration| format!("{:.3}", d.as_secs_f64());
+        for (i, solve) in self.solves.iter().enumerate() {
+            // assisted solves have no averages of their own
+            let upto = times(&self.solves[..=i]);
+            let rolling = |n| {
+                let average = average(&upto, n).filter(|_| !solve.assisted);
+                average.map(secs).unwrap_or_default()
+            };
+            let mut row = vec![
+                (i + 1).to_string(),
+                secs(solve.time),
+                rolling(5),
+                rolling(12),
+                solve.twists.to_string(),
+                solve.seed.map(|s| s.to_string()).unwrap_or_default(),
+                if solve.assisted { "yes" } else { "" }.to_string(),
+            ];
+            for step in 1..=steps {
+                let split = solve.splits.iter().find(|&&(s, _)| s == step);
+                row.push(split.map(|&(_, t)| secs(t)).unwrap_or_default());
+            }
+            out += &row.join(",");
+            out += "\n";
+        }
+        out
+    }
+
+    pub fn read_csv(src: &str) -> Result<Self, SessionError> {
+        let mut lines = src
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty());
+        let Some((_, header)) = lines.next() else {
+            return Ok(Self::default());
+        };
+        let header: Vec<&str> = header.split(',').map(str::trim).collect();
+        let column = |name: &str| header.iter().position(|&h| h == name);
+        let (Some(time), Some(twists), Some(seed)) =
+            (column("time"), column("twists"), column("seed"))
+        else {
+            return Err(SessionError {
+                line: 1,
+                msg: "expected `time`, `twists` and `seed` columns".to_string(),
+            });
+        };
+        // sessions written before solves could be assisted have no such column
+        let assisted = column("assisted");
+        let steps: Vec<(usize, usize)> = (header.iter().enumerate())
+            .filter_map(|(i, h)| Some((i, h.strip_prefix("step ")?.parse().ok()?)))
+            .collect();
+
+        let mut solves = Vec::new();
+        for (i, line) in lines {
+            let error = |msg: &str| SessionError {
+                line: i + 1,
+                msg: msg.to_string(),
+            };
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let field = |k: usize| fields.get(k).copied().unwrap_or("");
+            let secs = |s: &str| {
+                s.parse::<f64>()
+                    .ok()
+                    .and_then(|s| Duration::try_from_secs_f64(s).ok())
+            };
+            let mut splits = Vec::new();
+            for &(k, step) in &steps {
+                if !field(k).is_empty() {
+                    splits.push((step, secs(field(k)).ok_or_else(|| error("bad split"))?));
+                }
+            }
+            solves.push(Solve {
+                time: secs(field(time)).ok_or_else(|| error("bad time"))?,
+                twists: field(twists)
+                    .parse()
+                    .map_err(|_| error("bad twist count"))?,
+                seed: match field(seed) {
+                    "" => None,
+                    s => Some(s.parse().map_err(|_| error("bad seed"))?),
+                },
+                splits,
+                assisted: assisted.is_some_and(|k| !field(k).is_empty()),
+            });
+        }
+        Ok(Self { solves })
+    }
+}
+
+fn times(solves: &[Solve]) -> Vec<Duration> {
+    (solves.iter())
+        .filter(|s| !s.assisted)
+        .map(|s| s.time)
+        .collect()
+}
+
+fn average(times: &[Duration], n: usize) -> Option<Duration> {
+    if n < 3 || times.len() < n {
+        return None;
+    }
+    let mut times = times[times.len() - n..].to_vec();
+    times.sort();
+    Some(times[1..n - 1].iter().sum::<Duration>() / (n - 2) as u32)
+}
+
+// e.g. `12.34` or `1:02.35`
+pub fn format_time(d: Duration) -> String {
+    let cs = d.as_millis() / 10;
+    let (m, s, cs) = (cs / 6000, cs / 100 % 60, cs % 100);
+    if m > 0 {
+        format!("{m}:{s:02}.{cs:02}")
+    } else {
+        format!("{s}.{cs:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::puzzle::def::PuzzleDef;
+
+    // solves the scramble from `state`, after `assist` if asked
+    fn solve(state: &mut PuzzleState, assisted: bool, secs: u64) -> Solve {
+        let start = Instant::now();
+        let mut timer = Timer::new();
+        timer.arm(start);
+        if assisted {
+            timer.assist();
+        }
+        let mut undo = state.scramble.clone();
+        state.twist_move(&undo.pop().unwrap().inverse());
+        assert!(timer.update(start, state, 0).is_none());
+        for twist in undo.iter().rev() {
+            state.twist_move(&twist.inverse());
+        }
+        timer
+            .update(start + Duration::from_secs(secs), state, 0)
+            .expect("the puzzle is solved")
+    }
+
+    #[test]
+    fn assisted_solves_stay_out_of_the_statistics() {
+        let mut state = PuzzleState::new(Arc::new(PuzzleDef::builtin()));
+        let mut session = Session::default();
+        for (i, assisted) in [false, true, false].into_iter().enumerate() {
+            state.reset();
+            state.scramble(i as u64, 5);
+            session
+                .solves
+                .push(solve(&mut state, assisted, 10 * (i as u64 + 1)));
+        }
+        assert!(session.solves[1].assisted);
+        assert_eq!(session.timed(), 2);
+        assert_eq!(session.best(), Some(Duration::from_secs(10)));
+        assert_eq!(session.mean(), Some(Duration::from_secs(20)));
+
+        let csv = session.write_csv();
+        let read = Session::read_csv(&csv).unwrap();
+        assert_eq!(read.write_csv(), csv);
+        assert!(read.solves[1].assisted);
+    }
+}
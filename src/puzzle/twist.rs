@@ -13,4 +13,12 @@ impl Twist {
             rot: self.rot.inverse(),
         }
     }
+
+    // the twist of the grip `setup` takes to this one's, turning as `Permutation::conjugate`
+    pub fn conjugate(&self, setup: &Permutation) -> Twist {
+        Twist {
+            grip: setup.inverse().permute(self.grip),
+            rot: self.rot.conjugate(setup),
+        }
+    }
 }
@@ -1,9 +1,19 @@
+use std::{collections::VecDeque, sync::Arc};
+
 use eframe::{
-    egui::{Color32, Pos2, Ui, Vec2},
-    epaint::{PathShape, PathStroke},
+    egui::{Color32, Pos2, Rect, Ui, Vec2},
+    epaint::{PathShape, PathStroke, Stroke},
 };
 
-use crate::puzzle::{setup11c, state::PuzzleState, twist::Twist, viewsettings::ViewSettings};
+use crate::puzzle::{
+    def::{PuzzleDef, StickerShape},
+    notation::{Move, TwistKind},
+    perm::Permutation,
+    piece::{self, Piece},
+    state::PuzzleState,
+    twist::Twist,
+    viewsettings::ViewSettings,
+};
 
 pub struct Polygon {
     pub vertices: Vec<Vec2>,
@@ -44,208 +54,690 @@ impl Face {
 }
 
 pub enum Substicker<'a> {
-    Ridge(&'a Vec<Vec2>),
+    Ridge(&'a [Vec2]),
     Edge(Vec2, Vec2, Vec2, Vec2),
     Vertex(Vec2, Vec2, Vec2),
     None,
 }
 
+// something drawn in the puzzle view, in screen coordinates
+pub enum Shape {
+    Circle {
+        center: Pos2,
+        radius: f32,
+        color: Color32,
+    },
+    Polygon {
+        points: Vec<Pos2>,
+        color: Color32,
+    },
+}
+
+// a twist or reorientation as the view shows it; without a grip every piece turns
+#[derive(Clone)]
+struct Motion {
+    grip: Option<usize>,
+    rot: Permutation,
+}
+
+impl Motion {
+    fn moves(&self, piece: &Piece) -> bool {
+        self.grip.is_none_or(|g| piece.in_grip(g))
+    }
+}
+
+// a sticker as drawn: its piece, the cell and grip it sits at, and its polygons
+struct Sticker {
+    piece: usize,
+    cell: usize,
+    grip: usize,
+    polygons: Vec<(Vec<Pos2>, Color32)>,
+}
+
+impl Sticker {
+    fn center(&self) -> Pos2 {
+        let points = &self.polygons[0].0;
+        let sum = points.iter().fold(Vec2::ZERO, |sum, p| sum + p.to_vec2());
+        (sum / points.len() as f32).to_pos2()
+    }
+
+    fn lerp(&self, to: &Sticker, t: f32) -> Vec<(Vec<Pos2>, Color32)> {
+        self.polygons
+            .iter()
+            .zip(&to.polygons)
+            .map(|((a, ca), (b, cb))| {
+                let points = a.iter().zip(b).map(|(a, b)| a.lerp(*b, t)).collect();
+                (points, ca.lerp_to_gamma(*cb, t))
+            })
+            .collect()
+    }
+}
+
+// an arc three quarters of the way round `center`, turning clockwise on screen if `dir`
+// is positive, with a head at the end (and the start if `both_ways`)
+fn draw_arrow(center: Pos2, radius: f32, dir: f32, both_ways: bool, ui: &mut Ui) {
+    let stroke = Stroke::new(2.5, Color32::WHITE);
+    let sweep = 1.5 * std::f32::consts::PI * if dir < 0.0 { -1.0 } else { 1.0 };
+    let start = -0.75 * std::f32::consts::PI;
+    let at = |t: f32| center + radius * Vec2::angled(start + sweep * t);
+    let points: Vec<Pos2> = (0..=24).map(|i| at(i as f32 / 24.0)).collect();
+    ui.painter().add(PathShape::line(points, stroke));
+
+    let head = |tip: Pos2, towards: Pos2| {
+        let back = (towards - tip).normalized() * radius * 0.45;
+        let side = back.rot90() * 0.6;
+        ui.painter().add(PathShape::line(
+            vec![tip + back + side, tip, tip + back - side],
+            stroke,
+        ));
+    };
+    head(at(1.0), at(0.9));
+    if both_ways {
+        head(at(0.0), at(0.1));
+    }
+}
+
+fn draw_polygons(polygons: &[(Vec<Pos2>, Color32)], ui: &mut Ui) {
+    for (points, color) in polygons {
+        ui.painter().add(PathShape::convex_polygon(
+            points.clone(),
+            *color,
+            PathStroke::NONE,
+        ));
+    }
+}
+
 pub struct PuzzleView {
     pub state: PuzzleState,
     pub was_scrambled: bool,
     pub alt_view: bool,
-    pub filters: Vec<Vec<u8>>,
     pub filter_idx: usize,
-    pub faces: Vec<Face>,
     pub settings: ViewSettings,
+    pub faces: Vec<Face>,
+    // the index in `state.pieces` of the piece open in the inspector
+    pub inspected: Option<usize>,
+    // the symmetry taking the puzzle to the screen, which puts a chosen cell in the middle
+    orientation: Permutation,
+    // the pieces as drawn, turned by `orientation` and lagging behind `state` while twists
+    // animate
+    shown: Vec<Piece>,
+    queue: VecDeque<Motion>,
+    anim_start: Option<f64>,
 }
 
 impl PuzzleView {
+    pub fn new(def: PuzzleDef) -> Self {
+        let settings = ViewSettings::new(&def);
+        let faces = def
+            .faces
+            .iter()
+            .map(|f| {
+                Face::new(
+                    f.vertices.iter().map(|&v| v.into()).collect(),
+                    f.grip,
+                    f.mirrored,
+                )
+            })
+            .collect();
+        let state = PuzzleState::new(Arc::new(def));
+        let shown = state.pieces.clone();
+        let orientation = Permutation::identity(state.degree);
+        Self {
+            state,
+            was_scrambled: false,
+            alt_view: false,
+            filter_idx: 0,
+            settings,
+            faces,
+            inspected: None,
+            orientation,
+            shown,
+            queue: VecDeque::new(),
+            anim_start: None,
+        }
+    }
+
+    pub fn colors(&self, grip: usize) -> Color32 {
+        self.settings
+            .colors
+            .get(grip)
+            .copied()
+            .unwrap_or(Color32::BLACK)
+    }
+
+    fn substicker<'a>(&self, face_piece: &Piece, v: &'a [Vec2]) -> Substicker<'a> {
+        for (sig, shape) in &self.state.def.stickers {
+            if *sig == face_piece.sig {
+                return match *shape {
+                    StickerShape::Ridge => Substicker::Ridge(v),
+                    StickerShape::Edge([a1, a0, b0, b1]) => {
+                        Substicker::Edge(v[a1], v[a0], v[b0], v[b1])
+                    }
+                    StickerShape::Vertex([a, b, c]) => Substicker::Vertex(v[a], v[b], v[c]),
+                };
+            }
+        }
+        Substicker::None
+    }
+
     pub fn show_puzzle(&mut self, ui: &mut Ui) {
         self.draw_cells(ui);
-        for piece in &self.state.pieces {
+        let now = ui.input(|i| i.time);
+        let Some((twist, t)) = self.animation(now) else {
+            for sticker in self.stickers(&self.shown, |_| true) {
+                draw_polygons(&sticker.polygons, ui);
+            }
+            return;
+        };
+
+        let mut after = self.shown.clone();
+        for piece in &mut after {
+            if twist.moves(piece) {
+                *piece = piece.rotate(&twist.rot);
+            }
+        }
+        for sticker in self.stickers(&self.shown, |p| !twist.moves(p)) {
+            draw_polygons(&sticker.polygons, ui);
+        }
+        // each moving sticker slides from where the inverse twist puts it; a grip can be
+        // drawn on several faces of a cell, so take the nearest copy
+        let before = self.stickers(&self.shown, |p| twist.moves(p));
+        let inv = twist.rot.inverse();
+        for sticker in self.stickers(&after, |p| twist.moves(p)) {
+            let from = before
+                .iter()
+                .filter(|b| {
+                    b.piece == sticker.piece
+                        && b.cell == inv.permute(sticker.cell)
+                        && b.grip == inv.permute(sticker.grip)
+                })
+                .min_by(|a, b| {
+                    let d = |s: &Sticker| s.center().distance_sq(sticker.center());
+                    d(a).total_cmp(&d(b))
+                });
+            match from {
+                Some(from) => draw_polygons(&from.lerp(&sticker, t), ui),
+                None => draw_polygons(&sticker.polygons, ui),
+            }
+        }
+    }
+
+    pub fn twist_move(&mut self, twist: &Twist) {
+        self.state.twist_move(twist);
+        self.animate(twist);
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(twist) = self.state.undo() {
+            self.animate(&twist);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(twist) = self.state.redo() {
+            self.animate(&twist);
+        }
+    }
+
+    // makes a twist given as seen on screen, e.g. by clicking
+    pub fn twist_on_screen(&mut self, twist: &Twist) {
+        self.twist_move(&twist.conjugate(&self.orientation));
+    }
+
+    pub fn orientation(&self) -> &Permutation {
+        &self.orientation
+    }
+
+    // turns the whole puzzle so the cell drawn at `cell` moves to the middle; this changes
+    // only the view, not the state
+    pub fn recenter(&mut self, cell: usize) {
+        let orientation = self.orientation.product(self.state.def.cell_recenter(cell));
+        self.set_orientation(orientation);
+    }
+
+    pub fn reset_orientation(&mut self) {
+        self.set_orientation(Permutation::identity(self.state.degree));
+    }
+
+    fn set_orientation(&mut self, orientation: Permutation) {
+        let rot = self.orientation.inverse().product(&orientation);
+        self.orientation = orientation;
+        if !rot.is_identity() {
+            self.state.rotations += 1;
+            self.play(Motion { grip: None, rot });
+        }
+    }
+
+    // the grip of the puzzle drawn at `grip` on screen
+    fn home(&self, grip: usize) -> usize {
+        self.orientation.inverse().permute(grip)
+    }
+
+    // the pieces as they should be drawn once every twist has played
+    fn oriented(&self) -> Vec<Piece> {
+        let rot = piece::table(&self.orientation);
+        let mut pieces = self.state.pieces.clone();
+        for piece in &mut pieces {
+            piece.rotate_by(&rot);
+        }
+        pieces
+    }
+
+    // shows the current state at once, dropping any twists still animating
+    pub fn snap(&mut self) {
+        self.queue.clear();
+        self.anim_start = None;
+        self.shown = self.oriented();
+    }
+
+    fn animate(&mut self, twist: &Twist) {
+        let r = &self.orientation;
+        let motion = Motion {
+            grip: Some(r.permute(twist.grip)),
+            rot: twist.rot.conjugate(&r.inverse()),
+        };
+        self.play(motion);
+    }
+
+    fn play(&mut self, motion: Motion) {
+        if self.settings.animate {
+            self.queue.push_back(motion);
+        } else {
+            self.snap();
+        }
+    }
+
+    // finishes the twists whose time is up and returns the one in progress, with its
+    // eased progress from 0 to 1
+    fn animation(&mut self, now: f64) -> Option<(Motion, f32)> {
+        if !self.settings.animate {
+            self.queue.clear();
+        }
+        loop {
+            let Some(twist) = self.queue.front() else {
+                // changes not made through `twist_move`, `undo` or `redo` just appear
+                if self.shown != self.oriented() {
+                    self.snap();
+                }
+                return None;
+            };
+            // a long queue plays faster so the view doesn't fall behind
+            let duration = self.settings.twist_duration as f64 / self.queue.len() as f64;
+            let start = *self.anim_start.get_or_insert(now);
+            let t = ((now - start) / duration) as f32;
+            if t < 1.0 {
+                return Some((twist.clone(), t * t * (3.0 - 2.0 * t)));
+            }
+            for piece in &mut self.shown {
+                if twist.moves(piece) {
+                    *piece = piece.rotate(&twist.rot);
+                }
+            }
+            self.queue.pop_front();
+            self.anim_start = None;
+        }
+    }
+
+    fn stickers(&self, pieces: &[Piece], include: impl Fn(&Piece) -> bool) -> Vec<Sticker> {
+        let def = &self.state.def;
+        let margin = self.settings.edge_size + self.settings.gap_size;
+        let mut stickers = Vec::new();
+        for (i, piece) in pieces.iter().enumerate() {
+            if !include(piece) {
+                continue;
+            }
+            let inv_att = piece.attitude().inverse();
+            let filtered = (0..piece.degree())
+                .any(|g| piece.in_grip(g) && def.filters[self.filter_idx][inv_att.permute(g)] == 0);
             for cell in 0..piece.degree() {
-                let centered_piece = piece.rotate(&setup11c::cell_recenter(cell));
+                let centered_piece = piece.rotate(def.cell_recenter(cell));
                 for face in &self.faces {
                     if centered_piece.grip_state(face.grip) == 1 {
-                        let face_piece = centered_piece.rotate(&setup11c::face_recenter(face.grip));
-                        let secondary = setup11c::cell_recenter(cell).inverse().permute(face.grip);
+                        let face_piece = centered_piece.rotate(def.face_recenter(face.grip));
+                        let secondary = def.cell_recenter(cell).inverse().permute(face.grip);
                         let v = &face.polygon.vertices;
-                        let inv_att = piece.att.inverse().clone();
-                        let mut color = if self.alt_view {
-                            setup11c::colors(inv_att.permute(secondary))
+                        let color = if filtered {
+                            Color32::from_rgb(40, 40, 40)
+                        } else if self.alt_view {
+                            self.colors(inv_att.permute(secondary))
                         } else {
-                            setup11c::colors(inv_att.permute(cell))
+                            self.colors(inv_att.permute(cell))
                         };
-                        for g in 0..piece.degree() {
-                            if piece.grip_state(g) == 1
-                                && self.filters[self.filter_idx][inv_att.permute(g)] == 0
-                            {
-                                color = Color32::from_rgb(40, 40, 40);
-                                break;
-                            }
-                        }
 
-                        match setup11c::substicker(face_piece, v) {
+                        let mut polygons = Vec::new();
+                        match self.substicker(&face_piece, v) {
                             Substicker::Ridge(v) => {
-                                self.draw_ridge(
-                                    v,
-                                    cell,
-                                    self.settings.edge_size + self.settings.gap_size,
-                                    color,
-                                    ui,
-                                );
+                                polygons.push((self.ridge_points(v, cell, margin), color));
                                 if self.alt_view {
-                                    self.draw_ridge(
-                                        v,
-                                        cell,
-                                        self.settings.edge_size
-                                            + self.settings.gap_size
-                                            + self.settings.alt_ridge_width,
-                                        setup11c::colors(secondary),
-                                        ui,
-                                    );
+                                    polygons.push((
+                                        self.ridge_points(
+                                            v,
+                                            cell,
+                                            margin + self.settings.alt_ridge_width,
+                                        ),
+                                        self.colors(self.home(secondary)),
+                                    ));
                                 }
                             }
                             Substicker::Edge(a1, a0, b0, b1) => {
-                                self.draw_edge(a1, a0, b0, b1, cell, color, ui)
+                                polygons.push((self.edge_points(a1, a0, b0, b1, cell), color))
                             }
                             Substicker::Vertex(a, b, c) => {
-                                self.draw_vertex(a, b, c, cell, color, ui)
+                                polygons.push((self.vertex_points(a, b, c, cell), color))
                             }
-                            Substicker::None => (),
+                            Substicker::None => continue,
                         }
+                        stickers.push(Sticker {
+                            piece: i,
+                            cell,
+                            grip: secondary,
+                            polygons,
+                        });
                     }
                 }
             }
         }
+        stickers
     }
 
-    pub fn pointer_twist(&mut self, pos: Pos2, ccw: bool) {
+    // the cell drawn around `pos`
+    pub fn cell_at(&self, pos: Pos2) -> Option<usize> {
         let s = &self.settings;
-        for cell in 0..self.state.degree {
-            if pos.distance(self.calc_pos(cell, Vec2::ZERO)) < s.scale * s.cell_scale[cell].abs() {
-                let rel_pos = ((pos - s.offset) / s.scale - s.cell_pos[cell]) / s.cell_scale[cell];
-                let recenter = setup11c::cell_recenter(cell);
-
-                for face in &self.faces {
-                    if face.polygon.contains_pos(rel_pos) {
-                        let face_recenter = setup11c::face_recenter(face.grip);
-
-                        let mut rot = setup11c::face_rot(ccw ^ face.mirrored);
-                        let v = &face.polygon.vertices;
-                        let n = v.len();
-                        if self.edge_poly(v, 0).contains_pos(rel_pos)
-                            && self.edge_poly(v, n - 1).contains_pos(rel_pos)
-                        {
-                            rot = setup11c::vertex_rot(ccw ^ face.mirrored);
-                        } else {
-                            for i in 0..n {
-                                if self.edge_poly(v, i).contains_pos(rel_pos) {
-                                    let align = setup11c::face_rot(false).exp(i + 1);
-                                    if self.edge_poly(v, i + 1).contains_pos(rel_pos) {
-                                        rot = align
-                                            .product(&setup11c::vertex_rot(ccw ^ face.mirrored))
-                                            .product(&align.inverse());
-                                    } else {
-                                        rot = align
-                                            .product(&setup11c::edge_rot())
-                                            .product(&align.inverse());
-                                    }
-                                    break;
-                                }
-                            }
-                        }
-                        self.state.twist_move(&Twist {
-                            grip: cell,
-                            rot: recenter
-                                .product(&face_recenter)
-                                .product(&rot)
-                                .product(&face_recenter.inverse())
-                                .product(&recenter.inverse()),
-                        });
+        (0..self.state.degree).find(|&cell| {
+            pos.distance(self.calc_pos(cell, Vec2::ZERO)) < s.scale * s.cell_scale[cell].abs()
+        })
+    }
 
-                        break;
+    // the cell, face and kind of twist a click at `pos` would make
+    fn target(&self, pos: Pos2, ccw: bool) -> Option<(usize, &Face, TwistKind)> {
+        let s = &self.settings;
+        let cell = self.cell_at(pos)?;
+        let rel_pos = ((pos - s.offset) / s.scale - s.cell_pos[cell]) / s.cell_scale[cell];
+        let face = self
+            .faces
+            .iter()
+            .find(|face| face.polygon.contains_pos(rel_pos))?;
+        let ccw = ccw ^ face.mirrored;
+        let mut kind = TwistKind::Face { ccw };
+        let v = &face.polygon.vertices;
+        let n = v.len();
+        if self.edge_poly(v, 0).contains_pos(rel_pos)
+            && self.edge_poly(v, n - 1).contains_pos(rel_pos)
+        {
+            kind = TwistKind::Vertex { vertex: 0, ccw };
+        } else {
+            for i in 0..n {
+                if self.edge_poly(v, i).contains_pos(rel_pos) {
+                    if self.edge_poly(v, i + 1).contains_pos(rel_pos) {
+                        kind = TwistKind::Vertex {
+                            vertex: (i + 1) % n,
+                            ccw,
+                        };
+                    } else {
+                        kind = TwistKind::Edge { edge: i };
                     }
+                    break;
                 }
+            }
+        }
+        Some((cell, face, kind))
+    }
+
+    // the cell and face grip drawn at `pos`
+    pub fn face_at(&self, pos: Pos2) -> Option<(usize, usize)> {
+        self.target(pos, true)
+            .map(|(cell, face, _)| (cell, face.grip))
+    }
 
-                break;
+    pub fn pointer_twist(&mut self, pos: Pos2, ccw: bool) {
+        if let Some((cell, face, kind)) = self.target(pos, ccw) {
+            let twist = Move {
+                cell,
+                face: face.grip,
+                kind,
             }
+            .twist(&self.state.def);
+            self.twist_on_screen(&twist);
+        }
+    }
+
+    // highlights the region under the cursor, outlines every sticker a primary click there
+    // would move and shows which way it turns
+    pub fn show_preview(&self, pos: Pos2, ui: &mut Ui) {
+        if let Some((cell, face, kind)) = self.target(pos, true) {
+            self.draw_preview(cell, face, kind, ui);
         }
     }
 
-    pub fn draw_ridge(&self, v: &Vec<Vec2>, cell: usize, margin: f32, color: Color32, ui: &mut Ui) {
+    // the piece with a sticker under `pos`
+    pub fn piece_at(&self, pos: Pos2) -> Option<usize> {
+        let stickers = self.stickers(&self.shown, |_| true);
+        stickers.iter().rev().find_map(|sticker| {
+            let polygon = Polygon {
+                vertices: sticker.polygons[0].0.iter().map(|p| p.to_vec2()).collect(),
+            };
+            polygon.contains_pos(pos.to_vec2()).then_some(sticker.piece)
+        })
+    }
+
+    // outlines every sticker of the inspected piece, in all the cells it shows in
+    pub fn show_inspected(&self, ui: &mut Ui) {
+        let Some(piece) = self.inspected else {
+            return;
+        };
+        let stickers = self.stickers(&self.shown, |_| true);
+        for sticker in stickers.iter().filter(|s| s.piece == piece) {
+            let points = &sticker.polygons[0].0;
+            for (width, color) in [(5.0, Color32::BLACK), (2.5, Color32::WHITE)] {
+                ui.painter().add(PathShape::closed_line(
+                    points.clone(),
+                    Stroke::new(width, color),
+                ));
+            }
+        }
+    }
+
+    // the same in the colours given for pieces marked for the algorithm finder
+    pub fn show_marked(&self, marked: &[(usize, Color32)], ui: &mut Ui) {
+        if marked.is_empty() {
+            return;
+        }
+        for sticker in self.stickers(&self.shown, |_| true) {
+            let Some(&(_, color)) = marked.iter().find(|&&(piece, _)| piece == sticker.piece)
+            else {
+                continue;
+            };
+            let points = &sticker.polygons[0].0;
+            for (width, color) in [(5.0, Color32::BLACK), (2.5, color)] {
+                ui.painter().add(PathShape::closed_line(
+                    points.clone(),
+                    Stroke::new(width, color),
+                ));
+            }
+        }
+    }
+
+    // the same for a face picked from the keyboard
+    pub fn show_selection(&self, cell: usize, face: usize, ui: &mut Ui) {
+        if let Some(face) = self.faces.iter().find(|f| f.grip == face) {
+            self.draw_preview(cell, face, TwistKind::Face { ccw: true }, ui);
+        }
+    }
+
+    fn draw_preview(&self, cell: usize, face: &Face, kind: TwistKind, ui: &mut Ui) {
+        if !self.settings.preview || !self.queue.is_empty() {
+            return;
+        }
+        let v = &face.polygon.vertices;
         let n = v.len();
+        let m = self.settings.edge_size + self.settings.gap_size;
+        let region = match kind {
+            TwistKind::Face { .. } => v.clone(),
+            TwistKind::Vertex { vertex, .. } => {
+                let (a, b, c) = (v[(vertex + n - 1) % n], v[vertex], v[(vertex + 1) % n]);
+                vec![
+                    b,
+                    b + m * (c - b),
+                    b + m * (c + a - 2.0 * b),
+                    b + m * (a - b),
+                ]
+            }
+            TwistKind::Edge { edge } => self.edge_poly(v, edge).vertices,
+        };
+        let region: Vec<Pos2> = region.iter().map(|&p| self.calc_pos(cell, p)).collect();
+        let stroke = Stroke::new(2.0, Color32::WHITE);
+        ui.painter().add(PathShape::convex_polygon(
+            region.clone(),
+            Color32::from_white_alpha(60),
+            stroke,
+        ));
 
-        let mut points = Vec::new();
-        for i in 0..n {
-            points.push(self.calc_pos(
-                cell,
-                v[i] + margin * (v[(i + 1) % n] + v[(i + 2) % n] - 2.0 * v[i]),
-            ))
+        let twist = Move {
+            cell,
+            face: face.grip,
+            kind,
+        }
+        .twist(&self.state.def);
+        let stickers = self.stickers(&self.shown, |p| p.in_grip(twist.grip));
+        for sticker in stickers.iter().filter(|s| self.moves(s, &twist)) {
+            ui.painter().add(PathShape::closed_line(
+                sticker.polygons[0].0.clone(),
+                Stroke::new(1.5, Color32::WHITE),
+            ));
+        }
+
+        // the way the stickers of this cell turn on screen decides the arrow's direction
+        let center =
+            region.iter().fold(Vec2::ZERO, |sum, p| sum + p.to_vec2()) / region.len() as f32;
+        let center = center.to_pos2();
+        let mut after = self.shown.clone();
+        for piece in &mut after {
+            if piece.in_grip(twist.grip) {
+                *piece = piece.rotate(&twist.rot);
+            }
         }
+        let after = self.stickers(&after, |p| p.in_grip(twist.grip));
+        let turn: f32 = stickers
+            .iter()
+            .filter(|s| s.cell == cell && self.moves(s, &twist))
+            .filter_map(|s| {
+                let to = after.iter().find(|a| {
+                    a.piece == s.piece && a.cell == cell && a.grip == twist.rot.permute(s.grip)
+                })?;
+                let (a, b) = (s.center() - center, to.center() - center);
+                Some(a.x * b.y - a.y * b.x)
+            })
+            .sum();
+        let radius = region
+            .iter()
+            .map(|p| p.distance(center))
+            .fold(f32::INFINITY, f32::min)
+            * 0.6;
+        let both_ways = matches!(kind, TwistKind::Edge { .. });
+        draw_arrow(center, radius.max(8.0), turn.signum(), both_ways, ui);
+    }
 
-        ui.painter()
-            .add(PathShape::convex_polygon(points, color, PathStroke::NONE));
+    // whether `twist` moves the sticker: its piece goes elsewhere or turns, as the stickers
+    // round the face of a face twist do, or the sticker itself goes to another cell or grip
+    fn moves(&self, sticker: &Sticker, twist: &Twist) -> bool {
+        let shown = &self.shown[sticker.piece];
+        let rot = &twist.rot;
+        shown.in_grip(twist.grip)
+            && (piece::bits(shown.sig).any(|g| rot.permute(g) != g)
+                || rot.permute(sticker.cell) != sticker.cell
+                || rot.permute(sticker.grip) != sticker.grip)
     }
 
-    pub fn draw_edge(
-        &self,
-        a1: Vec2,
-        a0: Vec2,
-        b0: Vec2,
-        b1: Vec2,
-        cell: usize,
-        color: Color32,
-        ui: &mut Ui,
-    ) {
+    pub fn ridge_points(&self, v: &[Vec2], cell: usize, margin: f32) -> Vec<Pos2> {
+        let n = v.len();
+        (0..n)
+            .map(|i| {
+                self.calc_pos(
+                    cell,
+                    v[i] + margin * (v[(i + 1) % n] + v[(i + n - 1) % n] - 2.0 * v[i]),
+                )
+            })
+            .collect()
+    }
+
+    pub fn edge_points(&self, a1: Vec2, a0: Vec2, b0: Vec2, b1: Vec2, cell: usize) -> Vec<Pos2> {
         let edge = self.settings.edge_size;
         let gap = self.settings.gap_size;
-        ui.painter().add(PathShape::convex_polygon(
-            vec![
-                self.calc_pos(cell, a0 + (edge + gap) * (b0 - a0)),
-                self.calc_pos(cell, b0 + (edge + gap) * (a0 - b0)),
-                self.calc_pos(cell, b0 + (edge + gap) * (a0 - b0) + edge * (b1 - b0)),
-                self.calc_pos(cell, a0 + (edge + gap) * (b0 - a0) + edge * (a1 - a0)),
-            ],
-            color,
-            PathStroke::NONE,
-        ));
+        vec![
+            self.calc_pos(cell, a0 + (edge + gap) * (b0 - a0)),
+            self.calc_pos(cell, b0 + (edge + gap) * (a0 - b0)),
+            self.calc_pos(cell, b0 + (edge + gap) * (a0 - b0) + edge * (b1 - b0)),
+            self.calc_pos(cell, a0 + (edge + gap) * (b0 - a0) + edge * (a1 - a0)),
+        ]
     }
 
-    pub fn draw_vertex(&self, a: Vec2, b: Vec2, c: Vec2, cell: usize, color: Color32, ui: &mut Ui) {
-        ui.painter().add(PathShape::convex_polygon(
-            vec![
-                self.calc_pos(cell, b),
-                self.calc_pos(cell, b + self.settings.edge_size * (c - b)),
-                self.calc_pos(cell, b + self.settings.edge_size * (c + a - 2.0 * b)),
-                self.calc_pos(cell, b + self.settings.edge_size * (a - b)),
-            ],
-            color,
-            PathStroke::NONE,
-        ));
+    pub fn vertex_points(&self, a: Vec2, b: Vec2, c: Vec2, cell: usize) -> Vec<Pos2> {
+        vec![
+            self.calc_pos(cell, b),
+            self.calc_pos(cell, b + self.settings.edge_size * (c - b)),
+            self.calc_pos(cell, b + self.settings.edge_size * (c + a - 2.0 * b)),
+            self.calc_pos(cell, b + self.settings.edge_size * (a - b)),
+        ]
     }
 
     pub fn draw_cells(&self, ui: &mut Ui) {
+        for shape in self.cell_shapes() {
+            match shape {
+                Shape::Circle {
+                    center,
+                    radius,
+                    color,
+                } => {
+                    ui.painter().circle_filled(center, radius, color);
+                }
+                Shape::Polygon { points, color } => {
+                    ui.painter()
+                        .add(PathShape::convex_polygon(points, color, PathStroke::NONE));
+                }
+            }
+        }
+    }
+
+    fn cell_shapes(&self) -> Vec<Shape> {
+        let mut shapes = Vec::new();
         for cell in 0..self.state.degree {
-            ui.painter().circle_filled(
-                self.calc_pos(cell, Vec2::ZERO),
-                self.settings.cell_scale[cell].abs() * self.settings.scale,
-                setup11c::colors(cell),
-            );
-            ui.painter().add(PathShape::convex_polygon(
-                self.settings
+            shapes.push(Shape::Circle {
+                center: self.calc_pos(cell, Vec2::ZERO),
+                radius: self.settings.cell_scale[cell].abs() * self.settings.scale,
+                color: self.colors(self.home(cell)),
+            });
+            shapes.push(Shape::Polygon {
+                points: self
+                    .settings
                     .cell_outline
                     .iter()
                     .map(|p| self.calc_pos(cell, *p))
                     .collect(),
-                Color32::BLACK,
-                PathStroke::NONE,
-            ));
+                color: Color32::BLACK,
+            });
         }
+        shapes
     }
 
-    pub fn edge_poly(&self, v: &Vec<Vec2>, i: usize) -> Polygon {
+    // everything `show_puzzle` draws once twists finish animating, back to front, with the
+    // area of the screen it covers
+    pub fn shapes(&self) -> (Rect, Vec<Shape>) {
+        let mut shapes = self.cell_shapes();
+        for sticker in self.stickers(&self.oriented(), |_| true) {
+            for (points, color) in sticker.polygons {
+                shapes.push(Shape::Polygon { points, color });
+            }
+        }
+        let s = &self.settings;
+        let bounds = self.layout_bounds();
+        let rect = Rect::from_min_max(
+            s.offset + bounds.min.to_vec2() * s.scale,
+            s.offset + bounds.max.to_vec2() * s.scale,
+        );
+        (rect, shapes)
+    }
+
+    pub fn edge_poly(&self, v: &[Vec2], i: usize) -> Polygon {
         let n = v.len();
         Polygon {
             vertices: vec![
@@ -261,8 +753,83 @@ impl PuzzleView {
         }
     }
 
+    // the cells' bounding box in layout units
+    fn layout_bounds(&self) -> Rect {
+        let s = &self.settings;
+        let mut bounds = Rect::NOTHING;
+        for (pos, scale) in s.cell_pos.iter().zip(&s.cell_scale) {
+            bounds = bounds.union(Rect::from_center_size(
+                pos.to_pos2(),
+                Vec2::splat(2.0 * scale.abs()),
+            ));
+        }
+        bounds
+    }
+
+    // fits the layout into `rect`, then applies the zoom and pan
+    pub fn fit(&mut self, rect: Rect) {
+        let bounds = self.layout_bounds();
+        let s = &mut self.settings;
+        let fit = 0.95 * (rect.width() / bounds.width()).min(rect.height() / bounds.height());
+        s.scale = fit * s.zoom;
+        s.offset = rect.center() + s.pan - bounds.center().to_vec2() * s.scale;
+    }
+
+    // zooms by `factor`, keeping the point under `pos` in place
+    pub fn zoom_at(&mut self, pos: Pos2, factor: f32) {
+        let center = self.layout_bounds().center().to_vec2();
+        let s = &mut self.settings;
+        let zoom = (s.zoom * factor).clamp(0.2, 20.0);
+        let factor = zoom / s.zoom;
+        s.pan += (pos - s.offset - center * s.scale) * (1.0 - factor);
+        s.offset = pos - (pos - s.offset) * factor;
+        s.scale *= factor;
+        s.zoom = zoom;
+    }
+
+    pub fn pan_by(&mut self, delta: Vec2) {
+        self.settings.pan += delta;
+        self.settings.offset += delta;
+    }
+
+    pub fn reset_view(&mut self) {
+        self.settings.zoom = 1.0;
+        self.settings.pan = Vec2::ZERO;
+    }
+
+    // whether a click at `pos` would twist something
+    pub fn hits(&self, pos: Pos2) -> bool {
+        self.target(pos, true).is_some()
+    }
+
     pub fn calc_pos(&self, cell: usize, v: Vec2) -> Pos2 {
         let s = &self.settings;
         s.offset + ((s.cell_pos[cell] + v * s.cell_scale[cell]) * s.scale)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_twist_preview_outlines_the_face_turned() {
+        let view = PuzzleView::new(PuzzleDef::builtin());
+        let twist = Move {
+            cell: 0,
+            face: 1,
+            kind: TwistKind::Face { ccw: true },
+        }
+        .twist(&view.state.def);
+        let stickers = view.stickers(&view.shown, |p| p.in_grip(twist.grip));
+        let on_face: Vec<&Sticker> = (stickers.iter())
+            .filter(|s| s.cell == 0 && s.grip == 1)
+            .collect();
+        // all but the face's own centre go round it
+        let centre = |s: &Sticker| view.shown[s.piece].sig == 0b11;
+        assert!(on_face.iter().any(|s| !centre(s)));
+        for sticker in on_face {
+            assert_eq!(view.moves(sticker, &twist), !centre(sticker));
+        }
+    }
+}
@@ -1,6 +1,6 @@
-use eframe::egui::{Pos2, Vec2};
+use eframe::egui::{Color32, Pos2, Vec2};
 
-use crate::puzzle::setup11c;
+use crate::puzzle::def::{DefError, PuzzleDef};
 
 pub struct ViewSettings {
     pub cell_pos: Vec<Vec2>,
@@ -9,21 +9,133 @@ pub struct ViewSettings {
     pub edge_size: f32,
     pub gap_size: f32,
     pub alt_ridge_width: f32,
+    // the screen transform, refitted to the panel every frame from `zoom` and `pan`
     pub scale: f32,
     pub offset: Pos2,
+    pub zoom: f32,
+    pub pan: Vec2,
+    pub animate: bool,
+    // seconds per twist
+    pub twist_duration: f32,
+    pub preview: bool,
+    pub colors: Vec<Color32>,
 }
 
 impl ViewSettings {
-    pub fn default() -> Self {
+    pub fn new(def: &PuzzleDef) -> Self {
         Self {
-            cell_pos: setup11c::cell_positions(),
-            cell_scale: setup11c::cell_scales(),
-            cell_outline: setup11c::cell_outline(),
+            cell_pos: def.cell_positions.iter().map(|&p| p.into()).collect(),
+            cell_scale: def.cell_scales.clone(),
+            cell_outline: def.cell_outline.iter().map(|&p| p.into()).collect(),
             edge_size: 0.13,
             gap_size: 0.07,
             alt_ridge_width: 0.05,
             scale: 120.0,
             offset: Pos2::new(600.0, 540.0),
+            zoom: 1.0,
+            pan: Vec2::ZERO,
+            animate: true,
+            twist_duration: 0.15,
+            preview: true,
+            colors: def
+                .colors
+                .iter()
+                .map(|&[r, g, b]| Color32::from_rgb(r, g, b))
+                .collect(),
         }
     }
+
+    // the adjustable settings as `key: values` lines, for eframe's storage
+    pub fn write(&self) -> String {
+        format!(
+            "edge_size: {}\ngap_size: {}\nalt_ridge_width: {}\nzoom: {}\npan: {} {}\n\
+             animate: {}\ntwist_duration: {}\npreview: {}\n",
+            self.edge_size,
+            self.gap_size,
+            self.alt_ridge_width,
+            self.zoom,
+            self.pan.x,
+            self.pan.y,
+            self.animate,
+            self.twist_duration,
+            self.preview,
+        )
+    }
+
+    // unknown or malformed lines keep their current value
+    pub fn load(&mut self, src: &str) {
+        for line in src.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            let float = |field: &mut f32| {
+                if let Ok(v) = value.parse() {
+                    *field = v;
+                }
+            };
+            let flag = |field: &mut bool| {
+                if let Ok(v) = value.parse() {
+                    *field = v;
+                }
+            };
+            match key.trim() {
+                "edge_size" => float(&mut self.edge_size),
+                "gap_size" => float(&mut self.gap_size),
+                "alt_ridge_width" => float(&mut self.alt_ridge_width),
+                "zoom" => float(&mut self.zoom),
+                "pan" => {
+                    if let Some((x, y)) = value.split_once(' ')
+                        && let (Ok(x), Ok(y)) = (x.trim().parse(), y.trim().parse())
+                    {
+                        self.pan = Vec2::new(x, y);
+                    }
+                }
+                "animate" => flag(&mut self.animate),
+                "twist_duration" => float(&mut self.twist_duration),
+                "preview" => flag(&mut self.preview),
+                _ => (),
+            }
+        }
+    }
+
+    // a colour scheme is written as the `color` lines of a puzzle definition
+    pub fn write_colors(&self, def: &PuzzleDef) -> String {
+        let mut out = format!("# {} colour scheme\n", def.name);
+        for (n, c) in self.colors.iter().enumerate() {
+            out += &format!("color {n}: {} {} {}\n", c.r(), c.g(), c.b());
+        }
+        out
+    }
+
+    pub fn load_colors(&mut self, src: &str) -> Result<(), DefError> {
+        let mut colors = self.colors.clone();
+        for (i, line) in src.lines().enumerate() {
+            let error = |msg: String| DefError::Parse { line: i + 1, msg };
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let parsed = line.strip_prefix("color ").and_then(|rest| {
+                let (n, rgb) = rest.split_once(':')?;
+                let rgb: Vec<u8> = rgb
+                    .split_whitespace()
+                    .map(|v| v.parse().ok())
+                    .collect::<Option<_>>()?;
+                Some((
+                    n.trim().parse::<usize>().ok()?,
+                    <[u8; 3]>::try_from(rgb).ok()?,
+                ))
+            });
+            let Some((n, [r, g, b])) = parsed else {
+                return Err(error("expected `color N: r g b`".to_string()));
+            };
+            let slot = colors
+                .get_mut(n)
+                .ok_or_else(|| error(format!("`color {n}` out of range")))?;
+            *slot = Color32::from_rgb(r, g, b);
+        }
+        self.colors = colors;
+        Ok(())
+    }
 }